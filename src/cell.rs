@@ -0,0 +1,130 @@
+//! Transform the contents of a `Cell<T>` by value.
+//!
+//! `Cell::take()` needs `T: Default`, and `Cell::replace()` needs the new value before the old one can be consumed.
+//! `take()` here works like the crate-level `take()` instead, moving the value out of the cell and writing the
+//! closure's result back.
+
+use core::cell::{Cell, RefCell};
+use core::mem;
+use core::ptr;
+use std::thread_local;
+use std::vec::Vec;
+
+use exit_on_panic::exit_on_panic;
+use placeholder::Placeholder;
+
+thread_local! {
+    static ACTIVE: RefCell<Vec<*const ()>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Allows use of the value in a `Cell<T>` as though it was owned, as long as a `T` is put back afterwards.
+///
+/// Calling `take()` again on the same cell from within the closure panics before anything is read from the cell.
+/// Since that panic happens while the value is taken, it then terminates the program.
+/// # Safety
+/// While the closure runs, the cell doesn't hold a valid `T`. The closure must not access the cell in any way other
+/// than through this module, for example through `Cell::replace()` or `Cell::set()`.
+/// # Important
+/// Will terminate the program (see `policy::DefaultPolicy`) if the closure panics.
+///
+/// # Example
+/// ```
+/// use std::cell::Cell;
+///
+/// let cell = Cell::new(vec![1, 2, 3]);
+/// unsafe {
+///     take_mut::cell::take(&cell, |v| v.into_iter().map(|x| x * 2).collect());
+/// }
+/// assert_eq!(cell.into_inner(), [2, 4, 6]);
+/// ```
+#[track_caller]
+pub unsafe fn take<T, F>(cell: &Cell<T>, closure: F)
+  where F: FnOnce(T) -> T {
+    let _active = Active::enter(cell);
+    let slot = cell.as_ptr();
+    exit_on_panic(|| {
+        let old_t = ptr::read(slot);
+        let new_t = closure(old_t);
+        ptr::write(slot, new_t);
+    });
+}
+
+/// Like `take()`, but safe, as the cell holds `T::placeholder()` while the closure runs.
+///
+/// Any access to the cell from within the closure sees the placeholder. If the closure panics, the placeholder is
+/// left in the cell and the panic continues.
+pub fn take_or_placeholder<T, F>(cell: &Cell<T>, closure: F)
+  where T: Placeholder, F: FnOnce(T) -> T {
+    let old_t = cell.replace(T::placeholder());
+    cell.set(closure(old_t));
+}
+
+/// Marks a cell as taken on the current thread until dropped.
+///
+/// Zero-sized cells aren't tracked, as distinct ones can share an address and reading or writing them is a no-op.
+/// Neither are cells taken after the thread-local state was destroyed, e.g. from another thread-local's destructor.
+struct Active(Option<*const ()>);
+
+impl Active {
+    fn enter<T>(cell: &Cell<T>) -> Self {
+        if mem::size_of::<T>() == 0 {
+            return Active(None);
+        }
+        let addr = cell.as_ptr() as *const ();
+        let tracked = ACTIVE.try_with(|active| {
+            let mut active = active.borrow_mut();
+            if active.contains(&addr) {
+                panic!("take_mut::cell::take() called again on a Cell that is already taken");
+            }
+            active.push(addr);
+        });
+        Active(tracked.ok().map(|()| addr))
+    }
+}
+
+impl Drop for Active {
+    fn drop(&mut self) {
+        if let Some(addr) = self.0 {
+            let _ = ACTIVE.try_with(|active| active.borrow_mut().retain(|&a| a != addr));
+        }
+    }
+}
+
+
+#[test]
+fn cell_take() {
+    use std::string::String;
+    let cell = Cell::new(String::from("foo"));
+    unsafe {
+        take(&cell, |s| s + "bar");
+    }
+    assert_eq!(cell.into_inner(), "foobar");
+
+    let cell = Cell::new(String::from("foo"));
+    take_or_placeholder(&cell, |s| {
+        assert_eq!(cell.take(), "");
+        s + "baz"
+    });
+    assert_eq!(cell.into_inner(), "foobaz");
+}
+
+#[test]
+fn cell_take_rejects_reentrancy() {
+    use std::panic;
+    let cell = Cell::new(1);
+    let _active = Active::enter(&cell);
+    let res = panic::catch_unwind(panic::AssertUnwindSafe(|| Active::enter(&cell)));
+    assert!(res.is_err());
+}
+
+#[test]
+fn cell_take_nests_zero_sized_cells() {
+    struct Zst;
+    let cells = [Cell::new(Zst), Cell::new(Zst)];
+    unsafe {
+        take(&cells[0], |z| {
+            take(&cells[1], |z| z);
+            z
+        });
+    }
+}
@@ -0,0 +1,88 @@
+//! Reports which `take()` was active when a panic forced the process to terminate.
+
+use core::panic::Location;
+use std::any::Any;
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::string::String;
+use std::{thread, thread_local};
+use std::vec::Vec;
+
+thread_local! {
+    static ACTIVE: RefCell<Vec<&'static Location<'static>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Calls `f`, recording `location` as active while it runs.
+///
+/// If `f` panics, a report is written to stderr and the panic is resumed.
+/// If the thread-local state is already destroyed, e.g. when called from another thread-local's destructor, `f` is
+/// called untracked.
+pub fn track<R, F: FnOnce() -> R>(location: &'static Location<'static>, f: F) -> R {
+    if ACTIVE.try_with(|active| active.borrow_mut().push(location)).is_err() {
+        return f();
+    }
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    if let Err(ref payload) = result {
+        // Captured after unwinding, so this is the stack leading to the innermost take, not to the panic.
+        report(&**payload, &Backtrace::capture());
+    }
+    let _ = ACTIVE.try_with(|active| active.borrow_mut().pop());
+    match result {
+        Ok(ret) => ret,
+        Err(payload) => panic::resume_unwind(payload),
+    }
+}
+
+fn report(payload: &(dyn Any + Send), backtrace: &Backtrace) {
+    let message = if let Some(s) = payload.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.as_str()
+    } else {
+        "Box<dyn Any>"
+    };
+    let thread = thread::current();
+    let stderr = io::stderr();
+    let mut out = stderr.lock();
+    let _ = writeln!(out, "take_mut: panic while a value was taken, terminating the process");
+    let _ = writeln!(out, "  thread: {}", thread.name().unwrap_or("<unnamed>"));
+    let _ = writeln!(out, "  panic: {}", message);
+    let _ = writeln!(out, "  active takes, innermost first:");
+    let _ = ACTIVE.try_with(|active| {
+        for location in active.borrow().iter().rev() {
+            let _ = writeln!(out, "    {}", location);
+        }
+    });
+    if backtrace.status() == BacktraceStatus::Captured {
+        let _ = writeln!(out, "  backtrace leading to the innermost take (see the panic hook's output for the panic itself):");
+        let _ = writeln!(out, "{}", backtrace);
+    }
+}
+
+#[test]
+fn take_works_in_thread_local_destructor() {
+    use std::cell::Cell;
+    use std::thread;
+    struct TakeOnDrop(Cell<u32>);
+    impl Drop for TakeOnDrop {
+        fn drop(&mut self) {
+            let mut n = self.0.get();
+            ::take(&mut n, |n| n + 1);
+            assert_eq!(n, 2);
+        }
+    }
+    std::thread_local! {
+        static TAKE_ON_DROP: TakeOnDrop = const { TakeOnDrop(Cell::new(1)) };
+    }
+    // Register the destructor both before and after the take machinery's own thread-local state.
+    thread::spawn(|| {
+        TAKE_ON_DROP.with(|t| t.0.get());
+        ::take(&mut 0, |n| n);
+    }).join().unwrap();
+    thread::spawn(|| {
+        ::take(&mut 0, |n| n);
+        TAKE_ON_DROP.with(|t| t.0.get());
+    }).join().unwrap();
+}
@@ -0,0 +1,174 @@
+#[cfg(feature = "std")]
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use core::mem;
+use core::ptr;
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+use exit_on_panic::exit_on_panic;
+#[cfg(feature = "std")]
+use placeholder::Placeholder;
+
+/// Like `take()`, for every element of a slice, under a single panic guard.
+///
+/// # Important
+/// Will terminate the program (see `policy::DefaultPolicy`) if the closure panics.
+///
+/// # Example
+/// ```
+/// let mut words = [String::from("a"), String::from("b")];
+/// take_mut::take_each(&mut words, |w| w + "!");
+/// assert_eq!(words, ["a!", "b!"]);
+/// ```
+#[track_caller]
+pub fn take_each<T, F>(slice: &mut [T], mut closure: F)
+  where F: FnMut(T) -> T {
+    exit_on_panic(|| {
+        for slot in slice {
+            unsafe {
+                let old_t = ptr::read(slot);
+                let new_t = closure(old_t);
+                ptr::write(slot, new_t);
+            }
+        }
+    });
+}
+
+/// Like `take_each()`, but recovers from a panic in the closure the same way `take_or_recover()` does.
+///
+/// The element being transformed when the closure panics is replaced with the result of `recover`. Elements before
+/// it keep their new values, and elements after it are left untouched.
+/// # Important
+/// Will terminate the program (see `policy::DefaultPolicy`) if `recover` itself panics.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn take_each_or_recover<T, F, R>(slice: &mut [T], mut recover: R, mut closure: F)
+  where F: FnMut(T) -> T, R: FnMut() -> T {
+    for slot in slice {
+        ::take_or_recover(slot, &mut recover, &mut closure);
+    }
+}
+
+/// Like `take_each_or_recover()`, using `T::placeholder()` to recover.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn take_each_or_placeholder<T, F>(slice: &mut [T], closure: F)
+  where T: Placeholder, F: FnMut(T) -> T {
+    take_each_or_recover(slice, T::placeholder, closure)
+}
+
+/// Converts every element of a `Vec<T>` into a `U`, reusing the allocation if `T` and `U` have the same size and
+/// alignment.
+///
+/// Otherwise, this is the same as collecting into a new `Vec<U>`.
+/// If the closure panics, the elements converted so far and the ones not converted yet are dropped, the allocation
+/// is freed, and the panic continues.
+///
+/// # Example
+/// ```
+/// let v: Vec<u32> = vec![1, 2, 3];
+/// let v: Vec<i32> = take_mut::map_vec_in_place(v, |x| -(x as i32));
+/// assert_eq!(v, [-1, -2, -3]);
+/// ```
+#[cfg(feature = "std")]
+pub fn map_vec_in_place<T, U, F>(vec: Vec<T>, mut closure: F) -> Vec<U>
+  where F: FnMut(T) -> U {
+    if mem::size_of::<T>() != mem::size_of::<U>() || mem::align_of::<T>() != mem::align_of::<U>() {
+        return vec.into_iter().map(closure).collect();
+    }
+    let mut vec = mem::ManuallyDrop::new(vec);
+    let mut guard = MapGuard::<T, U> {
+        ptr: vec.as_mut_ptr(),
+        len: vec.len(),
+        capacity: vec.capacity(),
+        mapped: 0,
+        marker: PhantomData,
+    };
+    while guard.mapped < guard.len {
+        unsafe {
+            let slot = guard.ptr.add(guard.mapped);
+            let new_u = closure(ptr::read(slot));
+            ptr::write(slot as *mut U, new_u);
+        }
+        guard.mapped += 1;
+    }
+    let guard = mem::ManuallyDrop::new(guard);
+    unsafe { Vec::from_raw_parts(guard.ptr as *mut U, guard.len, guard.capacity) }
+}
+
+/// Owns the allocation of a `Vec<T>` that is being converted in place, element by element, to a `Vec<U>`.
+///
+/// Elements before `mapped` are `U`s, the element at `mapped` is being converted, and the rest are `T`s.
+#[cfg(feature = "std")]
+struct MapGuard<T, U> {
+    ptr: *mut T,
+    len: usize,
+    capacity: usize,
+    mapped: usize,
+    marker: PhantomData<(T, U)>,
+}
+
+#[cfg(feature = "std")]
+impl<T, U> Drop for MapGuard<T, U> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.ptr as *mut U, self.mapped));
+            let rest = self.ptr.add(self.mapped + 1);
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(rest, self.len - self.mapped - 1));
+            drop(Vec::from_raw_parts(self.ptr, 0, self.capacity));
+        }
+    }
+}
+
+
+#[test]
+fn take_each_maps_all() {
+    let mut v = [1, 2, 3];
+    take_each(&mut v, |x| x * 2);
+    assert_eq!(v, [2, 4, 6]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn take_each_recovers_the_panicking_element() {
+    use std::panic;
+    let mut v = [1, 2, 3];
+    let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        take_each_or_placeholder(&mut v, |x| if x == 2 { panic!("expected panic") } else { x * 10 });
+    }));
+    assert!(res.is_err());
+    assert_eq!(v, [10, 0, 3]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn map_vec_in_place_reuses_and_cleans_up() {
+    use std::panic;
+    use std::rc::Rc;
+    use std::vec;
+
+    let v: Vec<u64> = vec![1, 2, 3];
+    let ptr = v.as_ptr() as usize;
+    let v: Vec<i64> = map_vec_in_place(v, |x| x as i64 - 2);
+    assert_eq!(v, [-1, 0, 1]);
+    assert_eq!(v.as_ptr() as usize, ptr);
+
+    let v: Vec<u8> = map_vec_in_place(vec![1u32, 2], |x| x as u8);
+    assert_eq!(v, [1, 2]);
+
+    let counter = Rc::new(());
+    let v = vec![counter.clone(), counter.clone(), counter.clone()];
+    let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        let mut n = 0;
+        map_vec_in_place(v, |rc| {
+            n += 1;
+            if n == 2 {
+                panic!("expected panic");
+            }
+            Some(rc)
+        })
+    }));
+    assert!(res.is_err());
+    assert_eq!(Rc::strong_count(&counter), 1);
+}
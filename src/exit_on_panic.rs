@@ -1,31 +1,93 @@
-/// Used to ensure an exit on panic.
-/// Call .done() to consume without exiting
-#[derive(Debug)]
-struct ExitOnSuddenDrop;
+use core::panic::Location;
 
-impl ExitOnSuddenDrop {
+#[cfg(feature = "diagnostics")]
+use diagnostics;
+use policy::{DefaultPolicy, PanicPolicy};
 
+/// A guard that terminates the process if it is dropped, most likely because of a panic.
+///
+/// Create it before code that must not unwind, for example while a value has been moved out of a `&mut T`, and
+/// call `.defuse()` once that code is done. If a panic unwinds through the guard instead, it terminates the process
+/// as decided by its `PanicPolicy`.
+///
+/// # Example
+/// ```
+/// use take_mut::AbortOnUnwind;
+///
+/// let mut v = vec![1, 2, 3];
+/// let guard = AbortOnUnwind::new();
+/// unsafe {
+///     let old = std::ptr::read(&v);
+///     std::ptr::write(&mut v, old.into_iter().rev().collect());
+/// }
+/// guard.defuse();
+/// assert_eq!(v, [3, 2, 1]);
+/// ```
+#[derive(Debug, Default)]
+#[must_use = "the guard terminates the process as soon as it is dropped"]
+pub struct AbortOnUnwind<P: PanicPolicy = DefaultPolicy> {
+    policy: P,
+}
+
+impl AbortOnUnwind {
+    /// Creates a guard using `DefaultPolicy`.
     pub fn new() -> Self {
-        ExitOnSuddenDrop
+        AbortOnUnwind::with_policy(DefaultPolicy)
+    }
+}
+
+impl<P: PanicPolicy> AbortOnUnwind<P> {
+    /// Creates a guard using the given policy.
+    pub fn with_policy(policy: P) -> Self {
+        AbortOnUnwind { policy }
     }
-    /// Consume `self` without exiting
-    pub fn done(self) {
-        ::std::mem::forget(self);
+
+    /// Consumes the guard without terminating the process.
+    pub fn defuse(self) {
+        ::core::mem::forget(self);
     }
 }
 
-impl Drop for ExitOnSuddenDrop {
+impl<P: PanicPolicy> Drop for AbortOnUnwind<P> {
     fn drop(&mut self) {
-        ::std::process::exit(101);
+        self.policy.terminate();
     }
 }
 
 
 /// Calls its closure.
-/// If the closure panics, kill the process.
+/// If the closure panics, terminate the process as decided by `DefaultPolicy`.
+///
+/// With the `diagnostics` feature, a report naming the caller of this function and the panic message is written to
+/// stderr first.
+///
+/// # Example
+/// ```
+/// let sum = take_mut::exit_on_panic(|| 1 + 2);
+/// assert_eq!(sum, 3);
+/// ```
+#[track_caller]
 pub fn exit_on_panic<R, F: FnOnce() -> R>(f: F) -> R {
-    let exiter = ExitOnSuddenDrop::new();
-    let result = f();
-    exiter.done();
+    exit_on_panic_with(DefaultPolicy, f)
+}
+
+/// Calls its closure.
+/// If the closure panics, terminate the process as decided by `policy`.
+#[track_caller]
+pub fn exit_on_panic_with<P: PanicPolicy, R, F: FnOnce() -> R>(policy: P, f: F) -> R {
+    exit_on_panic_at(policy, Location::caller(), f)
+}
+
+/// Like `exit_on_panic_with()`, reporting `location` as the caller.
+pub(crate) fn exit_on_panic_at<P: PanicPolicy, R, F: FnOnce() -> R>(policy: P, location: &'static Location<'static>, f: F) -> R {
+    let guard = AbortOnUnwind::with_policy(policy);
+    #[cfg(feature = "diagnostics")]
+    let result = diagnostics::track(location, f);
+    #[cfg(not(feature = "diagnostics"))]
+    let result = {
+        let _ = location;
+        f()
+    };
+    guard.defuse();
     result
-}
\ No newline at end of file
+}
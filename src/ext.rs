@@ -0,0 +1,115 @@
+#[cfg(feature = "std")]
+use placeholder::Placeholder;
+
+/// Method-call syntax for `take()` and its variants.
+///
+/// Implemented for every type. Since method calls auto-dereference, the methods are also available
+/// on a `&mut T` binding, operating on the `T` behind it.
+///
+/// # Example
+/// ```
+/// use take_mut::TakeExt;
+///
+/// struct Counter { name: String, hits: u32 }
+///
+/// let mut counter = Counter { name: String::from("a"), hits: 0 };
+/// counter.name.take_with(|name| name + "b");
+/// let old = counter.hits.take_and_return_with(|hits| (hits + 1, hits));
+/// assert_eq!((counter.name.as_str(), counter.hits, old), ("ab", 1, 0));
+///
+/// let name = &mut counter.name;
+/// name.take_with(|name| name.to_uppercase());
+/// assert_eq!(counter.name, "AB");
+/// ```
+pub trait TakeExt: Sized {
+    /// Same as `take(self, closure)`.
+    ///
+    /// # Important
+    /// Will terminate the program (see `policy::DefaultPolicy`) if the closure panics.
+    fn take_with<F>(&mut self, closure: F)
+      where F: FnOnce(Self) -> Self;
+
+    /// Same as `take_and_return(self, closure)`.
+    ///
+    /// # Important
+    /// Will terminate the program (see `policy::DefaultPolicy`) if the closure panics.
+    fn take_and_return_with<R, F>(&mut self, closure: F) -> R
+      where F: FnOnce(Self) -> (Self, R);
+
+    /// Same as `take_copy(self, closure)`.
+    fn take_copy_with<F>(&mut self, closure: F)
+      where Self: Copy, F: FnOnce(Self) -> Self;
+
+    /// Same as `try_take(self, closure)`.
+    ///
+    /// # Important
+    /// Will terminate the program (see `policy::DefaultPolicy`) if the closure panics.
+    fn try_take_with<E, F>(&mut self, closure: F) -> Result<(), E>
+      where F: FnOnce(Self) -> Result<Self, (Self, E)>;
+
+    /// Same as `take_or_recover(self, recover, closure)`.
+    ///
+    /// # Important
+    /// Will terminate the program (see `policy::DefaultPolicy`) if `recover` panics.
+    #[cfg(feature = "std")]
+    fn take_or_recover_with<R, F>(&mut self, recover: R, closure: F)
+      where F: FnOnce(Self) -> Self, R: FnOnce() -> Self;
+
+    /// Same as `take_or_placeholder(self, closure)`.
+    #[cfg(feature = "std")]
+    fn take_or_placeholder_with<F>(&mut self, closure: F)
+      where Self: Placeholder, F: FnOnce(Self) -> Self;
+}
+
+// A separate impl for `&mut T` would overlap with this one; auto-deref covers that case instead.
+impl<T> TakeExt for T {
+    #[track_caller]
+    fn take_with<F>(&mut self, closure: F)
+      where F: FnOnce(T) -> T {
+        ::take(self, closure)
+    }
+
+    #[track_caller]
+    fn take_and_return_with<R, F>(&mut self, closure: F) -> R
+      where F: FnOnce(T) -> (T, R) {
+        ::take_and_return(self, closure)
+    }
+
+    fn take_copy_with<F>(&mut self, closure: F)
+      where T: Copy, F: FnOnce(T) -> T {
+        ::take_copy(self, closure)
+    }
+
+    #[track_caller]
+    fn try_take_with<E, F>(&mut self, closure: F) -> Result<(), E>
+      where F: FnOnce(T) -> Result<T, (T, E)> {
+        ::try_take(self, closure)
+    }
+
+    #[cfg(feature = "std")]
+    #[track_caller]
+    fn take_or_recover_with<R, F>(&mut self, recover: R, closure: F)
+      where F: FnOnce(T) -> T, R: FnOnce() -> T {
+        ::take_or_recover(self, recover, closure)
+    }
+
+    #[cfg(feature = "std")]
+    #[track_caller]
+    fn take_or_placeholder_with<F>(&mut self, closure: F)
+      where T: Placeholder, F: FnOnce(T) -> T {
+        ::take_or_placeholder(self, closure)
+    }
+}
+
+
+#[test]
+fn it_takes_through_methods() {
+    use std::string::String;
+    let mut s = String::from("a");
+    {
+        let r = &mut s;
+        r.take_with(|s| s + "b");
+        assert_eq!(r.try_take_with(|s| if s.is_empty() { Ok(s) } else { Err((s, "not empty")) }), Err("not empty"));
+    }
+    assert_eq!(s, "ab");
+}
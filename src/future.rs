@@ -0,0 +1,273 @@
+//! Transform a value through a future, such as an `async` block.
+//!
+//! `take_async()` moves the value out and only writes the new one back once the future completes, so it must not
+//! be left unfinished. If the returned future is dropped early, because it was cancelled or because it panicked,
+//! the process is terminated as decided by `DefaultPolicy`, or by the policy given to `take_async_with_policy()`.
+//!
+//! `take_async_or_recover()` is the safe alternative: it fills the `&mut T` with a recovery value for as long as the
+//! future runs, so there's no hole to leave behind.
+
+use core::future::Future;
+use core::mem;
+use core::panic::Location;
+use core::pin::Pin;
+use core::ptr;
+use core::task::{Context, Poll};
+
+use exit_on_panic::exit_on_panic_at;
+use placeholder::Placeholder;
+use policy::{DefaultPolicy, PanicPolicy};
+
+/// Like the crate-level `take()`, but the closure returns a future producing the new `T`.
+///
+/// Nothing happens until the returned future is first polled.
+/// # Safety
+/// Once polled, the returned future must be either driven to completion or dropped. It must not be leaked, for
+/// example with `mem::forget()`, as the `&mut T` would be left without a valid value once the borrow ends.
+/// # Important
+/// Will terminate the program (see `policy::DefaultPolicy`) if the closure or the future panics, or if the future
+/// is dropped before completing.
+///
+/// # Example
+/// ```edition2018,no_run
+/// async fn append(s: &mut String) {
+///     unsafe {
+///         take_mut::take_async(s, |s| async move { s + "bar" }).await
+///     }
+/// }
+/// ```
+#[track_caller]
+pub unsafe fn take_async<'a, T, F, Fut>(mut_ref: &'a mut T, closure: F) -> TakeAsync<'a, T, F, Fut>
+  where F: FnOnce(T) -> Fut, Fut: Future<Output = T> {
+    take_async_with_policy(mut_ref, DefaultPolicy, closure)
+}
+
+/// Like `take_async()`, but terminates the program as decided by `policy` if the closure or the future panics, or
+/// if the future is dropped before completing.
+/// # Safety
+/// Same as `take_async()`.
+#[track_caller]
+pub unsafe fn take_async_with_policy<'a, T, P, F, Fut>(mut_ref: &'a mut T, policy: P, closure: F) -> TakeAsync<'a, T, F, Fut, P>
+  where P: PanicPolicy, F: FnOnce(T) -> Fut, Fut: Future<Output = T> {
+    TakeAsync {
+        slot: mut_ref,
+        state: State::Start(closure),
+        policy,
+        location: Location::caller(),
+    }
+}
+
+/// Like `take_async()`, but safe, as the `&mut T` holds the result of `recover` until the future completes.
+///
+/// `recover` is called when the returned future is first polled. If the future is dropped before completing, or
+/// panics, the recovery value is simply left in place.
+///
+/// # Example
+/// ```edition2018,no_run
+/// async fn append(s: &mut String) {
+///     take_mut::take_async_or_recover(s, String::new, |s| async move { s + "bar" }).await
+/// }
+/// ```
+pub fn take_async_or_recover<'a, T, F, R, Fut>(mut_ref: &'a mut T, recover: R, closure: F) -> TakeAsyncOrRecover<'a, T, F, R, Fut>
+  where F: FnOnce(T) -> Fut, R: FnOnce() -> T, Fut: Future<Output = T> {
+    TakeAsyncOrRecover {
+        slot: mut_ref,
+        state: State::Start((recover, closure)),
+    }
+}
+
+/// Like `take_async_or_recover()`, using `T::placeholder()` to recover.
+pub fn take_async_or_placeholder<'a, T, F, Fut>(mut_ref: &'a mut T, closure: F) -> TakeAsyncOrRecover<'a, T, F, fn() -> T, Fut>
+  where T: Placeholder, F: FnOnce(T) -> Fut, Fut: Future<Output = T> {
+    take_async_or_recover(mut_ref, T::placeholder, closure)
+}
+
+enum State<S, Fut> {
+    Start(S),
+    Running(Fut),
+    Done,
+}
+
+/// The future returned by `take_async()` and `take_async_with_policy()`.
+#[must_use = "futures do nothing unless polled"]
+pub struct TakeAsync<'a, T: 'a, F, Fut, P: PanicPolicy = DefaultPolicy> {
+    slot: &'a mut T,
+    state: State<F, Fut>,
+    policy: P,
+    location: &'static Location<'static>,
+}
+
+impl<'a, T, F, Fut, P> Future for TakeAsync<'a, T, F, Fut, P>
+  where P: PanicPolicy, F: FnOnce(T) -> Fut, Fut: Future<Output = T> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        // `Fut` is never moved once `state` is `Running`.
+        let this = unsafe { self.get_unchecked_mut() };
+        if let State::Start(_) = this.state {
+            let closure = match mem::replace(&mut this.state, State::Done) {
+                State::Start(closure) => closure,
+                _ => unreachable!(),
+            };
+            let slot = &mut *this.slot;
+            let fut = exit_on_panic_at(&this.policy, this.location, || closure(unsafe { ptr::read(slot) }));
+            this.state = State::Running(fut);
+        }
+        let new_t = match this.state {
+            State::Running(ref mut fut) => {
+                match exit_on_panic_at(&this.policy, this.location, || unsafe { Pin::new_unchecked(fut) }.poll(cx)) {
+                    Poll::Ready(new_t) => new_t,
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+            _ => panic!("`TakeAsync` polled after completion"),
+        };
+        unsafe {
+            ptr::write(this.slot, new_t);
+        }
+        this.state = State::Done;
+        Poll::Ready(())
+    }
+}
+
+impl<'a, T, F, Fut, P: PanicPolicy> Drop for TakeAsync<'a, T, F, Fut, P> {
+    fn drop(&mut self) {
+        if let State::Running(_) = self.state {
+            // Cancelled or unwinding with the value still taken.
+            self.policy.terminate();
+        }
+    }
+}
+
+/// The future returned by `take_async_or_recover()`.
+#[must_use = "futures do nothing unless polled"]
+pub struct TakeAsyncOrRecover<'a, T: 'a, F, R, Fut> {
+    slot: &'a mut T,
+    state: State<(R, F), Fut>,
+}
+
+impl<'a, T, F, R, Fut> Future for TakeAsyncOrRecover<'a, T, F, R, Fut>
+  where F: FnOnce(T) -> Fut, R: FnOnce() -> T, Fut: Future<Output = T> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        // `Fut` is never moved once `state` is `Running`.
+        let this = unsafe { self.get_unchecked_mut() };
+        if let State::Start(_) = this.state {
+            let (recover, closure) = match mem::replace(&mut this.state, State::Done) {
+                State::Start(start) => start,
+                _ => unreachable!(),
+            };
+            let old_t = mem::replace(this.slot, recover());
+            this.state = State::Running(closure(old_t));
+        }
+        let new_t = match this.state {
+            State::Running(ref mut fut) => match unsafe { Pin::new_unchecked(fut) }.poll(cx) {
+                Poll::Ready(new_t) => new_t,
+                Poll::Pending => return Poll::Pending,
+            },
+            _ => panic!("`TakeAsyncOrRecover` polled after completion"),
+        };
+        *this.slot = new_t;
+        this.state = State::Done;
+        Poll::Ready(())
+    }
+}
+
+
+#[cfg(test)]
+fn yield_then<T>(value: T) -> impl Future<Output = T> {
+    let mut value = Some(value);
+    let mut yielded = false;
+    ::core::future::poll_fn(move |cx| {
+        if yielded {
+            Poll::Ready(value.take().unwrap())
+        } else {
+            yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    })
+}
+
+#[cfg(test)]
+fn block_on<Fut: Future>(fut: Fut) -> Fut::Output {
+    let mut fut = ::core::pin::pin!(fut);
+    let mut cx = Context::from_waker(::core::task::Waker::noop());
+    loop {
+        if let Poll::Ready(ret) = fut.as_mut().poll(&mut cx) {
+            return ret;
+        }
+    }
+}
+
+#[test]
+fn take_async_completes() {
+    use std::string::String;
+    let mut s = String::from("foo");
+    block_on(unsafe { take_async(&mut s, |s| yield_then(s + "bar")) });
+    assert_eq!(s, "foobar");
+
+    block_on(take_async_or_recover(&mut s, String::new, |s| yield_then(s + "baz")));
+    assert_eq!(s, "foobarbaz");
+}
+
+#[test]
+fn take_async_or_recover_cancelled() {
+    use std::string::String;
+    let mut s = String::from("foo");
+    {
+        let mut fut = ::core::pin::pin!(take_async_or_recover(&mut s, || String::from("recovered"), |s| yield_then(s + "bar")));
+        let mut cx = Context::from_waker(::core::task::Waker::noop());
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+    }
+    assert_eq!(s, "recovered");
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn take_async_terminates_when_dropped() {
+    use std::{env, format, process::{Command, Stdio}, string::String};
+    use policy::Exit;
+    if let Some(child) = env::var_os("TAKE_MUT_TAKE_ASYNC_CHILD") {
+        let mut s = String::from("foo");
+        let mut cx = Context::from_waker(::core::task::Waker::noop());
+        if child == "exit" {
+            let mut fut = ::core::pin::pin!(unsafe { take_async_with_policy(&mut s, Exit(7), yield_then) });
+            assert!(fut.as_mut().poll(&mut cx).is_pending());
+        } else if child == "panic" {
+            let fut = unsafe { take_async(&mut s, |_| ::core::future::poll_fn(|_| -> Poll<String> { panic!("expected panic") })) };
+            let _ = ::core::pin::pin!(fut).poll(&mut cx);
+        } else {
+            let mut fut = ::core::pin::pin!(unsafe { take_async(&mut s, yield_then) });
+            assert!(fut.as_mut().poll(&mut cx).is_pending());
+        }
+        return;
+    }
+    // Line of the `take_async()` call in the "panic" child.
+    let take_line = line!() - 9;
+    let run_child = |child| Command::new(env::current_exe().unwrap())
+        .args(["--exact", "future::take_async_terminates_when_dropped", "--test-threads=1"])
+        .env("TAKE_MUT_TAKE_ASYNC_CHILD", child)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+    assert_eq!(run_child("exit").status.code(), Some(7));
+    if cfg!(feature = "diagnostics") {
+        let stderr = String::from_utf8(run_child("panic").stderr).unwrap();
+        assert!(stderr.contains(&format!("{}:{}", file!(), take_line)), "{}", stderr);
+    }
+    let status = run_child("default").status;
+    if cfg!(feature = "exit-on-panic") {
+        assert_eq!(status.code(), Some(101));
+    } else {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            // SIGABRT
+            assert_eq!(status.signal(), Some(6));
+        }
+        assert!(!status.success());
+    }
+}
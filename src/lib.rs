@@ -1,20 +1,103 @@
-//! This crate provides (at this time) a single function, `take()`.
+//! This crate provides `take()`, `take_or_recover()` and their `take_and_return()` counterparts.
 //!
 //! `take()` allows for taking `T` out of a `&mut T`, doing anything with it including consuming it, and producing another `T` to put back in the `&mut T`.
 //!
-//! During `take()`, if a panic occurs, the entire process will be exited, as there's no valid `T` to put back into the `&mut T`.
+//! During `take()`, if a panic occurs, the entire process will be aborted, as there's no valid `T` to put back into the `&mut T`.
+//! How that happens is decided by a `PanicPolicy`, see the `policy` module.
+//! The same guard is available as `AbortOnUnwind` and `exit_on_panic()` for use in other unsafe code.
+//! Use `take_or_recover()` to supply a replacement `T` for that case and let the panic continue instead.
+//! `take_or_default()` and `take_or_placeholder()` do the same with `T::default()` or `T::placeholder()`,
+//! and every other flavor that can recover has an `_or_placeholder` variant as well.
+//! `take_transactional()` recovers with a clone of the original `T` instead, rolling the change back.
+//! For `T: Copy`, `take_copy()` needs no guard at all, as the original `T` stays in place during the closure.
+//!
+//! `take_and_return()` and `take_and_return_or_recover()` additionally let the closure hand back a result alongside the new `T`.
+//! The `take!` macro wraps either of them in a `match`, leaving values that match none of its arms unchanged.
+//!
+//! `try_take()` and `try_take_then()` allow the transformation to fail, leaving the original `T` in place.
+//! The `TakeExt` trait offers the same as methods, e.g. `self.field.take_with(|f| ...)`.
+//!
+//! `take_each()` transforms every element of a slice under one panic guard, and `map_vec_in_place()` converts a
+//! `Vec<T>` into a `Vec<U>`, reusing its allocation where possible.
+//!
+//! `take2()`, `take3()` and the `TakeMany` trait take several values at once, under a single panic guard.
+//! `scope()` allows taking several values at once and moving them between each other, see the `scoped` module.
+//!
+//! `take_async()` and `take_async_or_recover()` transform a value through a future, see the `future` module.
+//! The `state` module helps with state machines written by hand in `Future::poll()` and `Iterator::next()`.
+//! The `machine` module provides a `StateMachine` trait and a `Machine` driver built on `take_and_return()`.
+//!
+//! The `cell` and `refcell` modules provide `take()` and its variants for the contents of a `Cell<T>` or a `RefCell<T>`.
+//! The `sync` module does the same for a `Mutex<T>` or `RwLock<T>`, poisoning the lock on panic.
+//! `Poisonable<T>` is a slot of its own that is left poisoned, rather than terminating the process, on panic.
 //!
 //! Contrast with `std::mem::replace()`, which allows for putting a different `T` into a `&mut T`, but requiring the new `T` to be available before being able to consume the old `T`.
+//!
+//! # Features
+//! - `std` (default): required by the `*_or_recover()` functions, which need to catch the panic.
+//!   Without it, the crate is `no_std`, and `policy::Abort` aborts through a double panic.
+//! - `diagnostics`: before terminating the process, write a report to stderr with the panic message,
+//!   the thread name, the call sites of the active `take()`s, and if `RUST_BACKTRACE` is set, a backtrace leading to
+//!   the innermost `take()`. The panic hook prints the backtrace of the panic itself.
+//!   Off by default, as it adds a `catch_unwind()` and thread-local bookkeeping to every guarded call.
+//! - `exit-on-panic`: make `policy::DefaultPolicy` exit with status code 101 instead of aborting.
+//! - `macros`: enable the procedural macros, `#[abort_handler]`, `#[by_value]` and `#[derive(Placeholder)]`.
+//! - `abort-handler`: without `std`, make `policy::Abort` call the function registered with `#[abort_handler]`
+//!   instead of panicking twice. Exactly one such function must be registered in the final binary.
+
+#![no_std]
 
+#[cfg(any(feature = "std", test))]
+extern crate std;
+#[cfg(feature = "macros")]
+extern crate take_mut_derive;
+
+#[cfg(feature = "std")]
+pub mod cell;
+#[cfg(feature = "diagnostics")]
+mod diagnostics;
+mod each;
 mod exit_on_panic;
+mod ext;
+pub mod future;
+#[cfg(feature = "std")]
+pub mod machine;
+mod many;
+mod placeholder;
+pub mod poisonable;
+pub mod policy;
+pub mod refcell;
+pub mod scoped;
+pub mod state;
+#[cfg(feature = "std")]
+pub mod sync;
 
-use exit_on_panic::exit_on_panic;
+pub use each::take_each;
+#[cfg(feature = "std")]
+pub use each::{map_vec_in_place, take_each_or_placeholder, take_each_or_recover};
+pub use exit_on_panic::{exit_on_panic, exit_on_panic_with, AbortOnUnwind};
+pub use ext::TakeExt;
+pub use future::{take_async, take_async_or_placeholder, take_async_or_recover, take_async_with_policy};
+pub use many::{take2, take3, TakeMany};
+#[cfg(feature = "std")]
+pub use many::{take2_or_placeholder, take2_or_recover, take3_or_placeholder, take3_or_recover};
+pub use placeholder::Placeholder;
+pub use poisonable::Poisonable;
+pub use policy::PanicPolicy;
+pub use scoped::scope;
+#[cfg(feature = "macros")]
+pub use take_mut_derive::{abort_handler, by_value, Placeholder};
+#[cfg(feature = "std")]
+use std::panic;
+#[cfg(test)]
+use std::{println, string::{String, ToString}};
 
 /// Allows use of a value pointed to by `&mut T` as though it was owned, as long as a `T` is made available afterwards.
 ///
 /// The closure must return a valid T.
 /// # Important
-/// Will exit the program (with status code 101) if the closure panics.
+/// Will terminate the program (see `policy::DefaultPolicy`) if the closure panics.
+/// For `T: Copy`, `take_copy()` avoids this.
 ///
 /// # Example
 /// ```
@@ -27,23 +110,412 @@ use exit_on_panic::exit_on_panic;
 ///     Foo // Return new Foo from closure, which goes back into the &mut Foo
 /// });
 /// ```
+#[track_caller]
 pub fn take<T, F>(mut_ref: &mut T, closure: F)
   where F: FnOnce(T) -> T {
-    use std::ptr;
-    exit_on_panic(|| {
+    take_and_return(mut_ref, |t| (closure(t), ()))
+}
+
+/// Like `take()`, but the closure also returns a value of type `R`, which is passed back to the caller.
+///
+/// # Important
+/// Will terminate the program (see `policy::DefaultPolicy`) if the closure panics.
+///
+/// # Example
+/// ```
+/// let mut v = vec![1, 2, 3];
+/// let len = take_mut::take_and_return(&mut v, |v| {
+///     let len = v.len();
+///     (v.into_iter().map(|x| x * 2).collect(), len)
+/// });
+/// assert_eq!(len, 3);
+/// assert_eq!(v, [2, 4, 6]);
+/// ```
+#[track_caller]
+pub fn take_and_return<T, R, F>(mut_ref: &mut T, closure: F) -> R
+  where F: FnOnce(T) -> (T, R) {
+    take_and_return_with_policy(mut_ref, policy::DefaultPolicy, closure)
+}
+
+/// Like `take()`, but terminates the program as decided by `policy` if the closure panics.
+///
+/// # Example
+/// ```
+/// use take_mut::policy::Abort;
+///
+/// let mut s = String::from("foo");
+/// take_mut::take_with_policy(&mut s, Abort, |s| s + "bar");
+/// assert_eq!(s, "foobar");
+/// ```
+#[track_caller]
+pub fn take_with_policy<T, P, F>(mut_ref: &mut T, policy: P, closure: F)
+  where P: PanicPolicy, F: FnOnce(T) -> T {
+    take_and_return_with_policy(mut_ref, policy, |t| (closure(t), ()))
+}
+
+/// Like `take_and_return()`, but terminates the program as decided by `policy` if the closure panics.
+#[track_caller]
+pub fn take_and_return_with_policy<T, R, P, F>(mut_ref: &mut T, policy: P, closure: F) -> R
+  where P: PanicPolicy, F: FnOnce(T) -> (T, R) {
+    use core::ptr;
+    exit_on_panic_with(policy, || {
         unsafe {
             let old_t = ptr::read(mut_ref);
-            let new_t = closure(old_t);
+            let (new_t, ret) = closure(old_t);
             ptr::write(mut_ref, new_t);
+            ret
+        }
+    })
+}
+
+/// Like `take()` for `T: Copy`, without a panic guard.
+///
+/// The closure gets a copy of the value, so the original is still in place if the closure panics, and the panic
+/// simply continues.
+///
+/// `take()` can't switch to this automatically: `core::mem::needs_drop::<T>() == false` does not mean that `T` is
+/// `Copy`. A `&mut U`, for example, needs no drop, but leaving a duplicate of it behind would be unsound.
+///
+/// # Example
+/// ```
+/// let mut n = 1;
+/// take_mut::take_copy(&mut n, |n| n * 2);
+/// assert_eq!(n, 2);
+/// ```
+pub fn take_copy<T, F>(mut_ref: &mut T, closure: F)
+  where T: Copy, F: FnOnce(T) -> T {
+    *mut_ref = closure(*mut_ref);
+}
+
+/// Like `take()`, but the transformation may fail, in which case the closure gives back the original `T` along with
+/// the error, and the error is returned.
+///
+/// # Important
+/// Will terminate the program (see `policy::DefaultPolicy`) if the closure panics.
+///
+/// # Example
+/// ```
+/// enum Port {
+///     Raw(String),
+///     Parsed(u16),
+/// }
+///
+/// let mut port = Port::Raw(String::from("eighty"));
+/// let res = take_mut::try_take(&mut port, |port| match port {
+///     Port::Raw(s) => match s.parse() {
+///         Ok(n) => Ok(Port::Parsed(n)),
+///         Err(e) => Err((Port::Raw(s), e)),
+///     },
+///     parsed => Ok(parsed),
+/// });
+/// assert!(res.is_err());
+/// assert!(matches!(port, Port::Raw(_)));
+/// ```
+#[track_caller]
+pub fn try_take<T, E, F>(mut_ref: &mut T, closure: F) -> Result<(), E>
+  where F: FnOnce(T) -> Result<T, (T, E)> {
+    take_and_return(mut_ref, |t| match closure(t) {
+        Ok(new_t) => (new_t, Ok(())),
+        Err((old_t, err)) => (old_t, Err(err)),
+    })
+}
+
+/// Like `try_take()`, but split into a fallible `prepare` step, which only borrows the `T`, and an infallible
+/// `closure`, which consumes it together with the prepared value.
+///
+/// As `prepare` never takes ownership, it can use `?` freely. If it fails, the `T` is left untouched and the error
+/// is returned.
+/// # Important
+/// Will terminate the program (see `policy::DefaultPolicy`) if `closure` panics.
+///
+/// # Example
+/// ```
+/// enum Port {
+///     Raw(String),
+///     Parsed(u16),
+/// }
+///
+/// fn parse(port: &mut Port) -> Result<(), std::num::ParseIntError> {
+///     take_mut::try_take_then(port, |port| match *port {
+///         Port::Raw(ref s) => Ok(Some(s.parse()?)),
+///         Port::Parsed(_) => Ok(None),
+///     }, |port, parsed| match parsed {
+///         Some(n) => Port::Parsed(n),
+///         None => port,
+///     })
+/// }
+///
+/// let mut port = Port::Raw(String::from("80"));
+/// parse(&mut port).unwrap();
+/// assert!(matches!(port, Port::Parsed(80)));
+/// ```
+#[track_caller]
+pub fn try_take_then<T, U, E, P, F>(mut_ref: &mut T, prepare: P, closure: F) -> Result<(), E>
+  where P: FnOnce(&T) -> Result<U, E>, F: FnOnce(T, U) -> T {
+    let prepared = prepare(mut_ref)?;
+    take(mut_ref, |t| closure(t, prepared));
+    Ok(())
+}
+
+/// Like `take()`, but instead of exiting the program when the closure panics, `recover` is called to produce a new `T`.
+///
+/// The recovered `T` is written into the `&mut T` and the original panic is resumed, so it can be caught further up the stack.
+/// # Important
+/// Will terminate the program (see `policy::DefaultPolicy`) if `recover` itself panics.
+///
+/// # Example
+/// ```
+/// let mut v = vec![1, 2, 3];
+/// let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+///     take_mut::take_or_recover(&mut v, Vec::new, |v| {
+///         drop(v);
+///         panic!("oops");
+///     });
+/// }));
+/// assert!(res.is_err());
+/// assert!(v.is_empty());
+/// ```
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn take_or_recover<T, F, R>(mut_ref: &mut T, recover: R, closure: F)
+  where F: FnOnce(T) -> T, R: FnOnce() -> T {
+    take_and_return_or_recover(mut_ref, recover, |t| (closure(t), ()))
+}
+
+/// Like `take_or_recover()`, using `T::default()` to recover.
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+///
+/// let mut map = HashMap::new();
+/// map.insert("a", 1);
+/// take_mut::take_or_default(&mut map, |map| {
+///     map.into_iter().map(|(k, v)| (k, v + 1)).collect()
+/// });
+/// assert_eq!(map["a"], 2);
+/// ```
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn take_or_default<T, F>(mut_ref: &mut T, closure: F)
+  where T: Default, F: FnOnce(T) -> T {
+    take_or_recover(mut_ref, T::default, closure)
+}
+
+/// Like `take_or_recover()`, using `T::placeholder()` to recover.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn take_or_placeholder<T, F>(mut_ref: &mut T, closure: F)
+  where T: Placeholder, F: FnOnce(T) -> T {
+    take_or_recover(mut_ref, T::placeholder, closure)
+}
+
+/// Like `take_and_return()`, but recovers from a panic in the closure the same way `take_or_recover()` does.
+///
+/// # Important
+/// Will terminate the program (see `policy::DefaultPolicy`) if `recover` itself panics.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn take_and_return_or_recover<T, R, F, G>(mut_ref: &mut T, recover: G, closure: F) -> R
+  where F: FnOnce(T) -> (T, R), G: FnOnce() -> T {
+    use std::ptr;
+    unsafe {
+        let old_t = ptr::read(mut_ref);
+        match panic::catch_unwind(panic::AssertUnwindSafe(|| closure(old_t))) {
+            Ok((new_t, ret)) => {
+                ptr::write(mut_ref, new_t);
+                ret
+            }
+            Err(err) => {
+                ptr::write(mut_ref, exit_on_panic(recover));
+                panic::resume_unwind(err);
+            }
+        }
+    }
+}
+
+/// Like `take_or_recover()`, recovering with a clone of the value taken before the closure ran.
+///
+/// On a panic in the closure, the `&mut T` is rolled back to its original value and the panic continues.
+///
+/// # Example
+/// ```
+/// let mut config = vec![String::from("a")];
+/// let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+///     take_mut::take_transactional(&mut config, |mut config| {
+///         config.clear();
+///         panic!("oops");
+///     });
+/// }));
+/// assert!(res.is_err());
+/// assert_eq!(config, ["a"]);
+/// ```
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn take_transactional<T, F>(mut_ref: &mut T, closure: F)
+  where T: Clone, F: FnOnce(T) -> T {
+    let snapshot = mut_ref.clone();
+    take_or_recover(mut_ref, || snapshot, closure)
+}
+
+/// Like `take_transactional()`, but in debug builds also checks the new value with `validate` before writing it.
+///
+/// If `validate` returns `false`, the `&mut T` is rolled back to its original value and the call panics.
+/// In release builds, `validate` is not called.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn take_transactional_validated<T, V, F>(mut_ref: &mut T, validate: V, closure: F)
+  where T: Clone, V: FnOnce(&T) -> bool, F: FnOnce(T) -> T {
+    take_transactional(mut_ref, |t| {
+        let new_t = closure(t);
+        if cfg!(debug_assertions) && !validate(&new_t) {
+            panic!("take_transactional_validated: new value failed validation, rolled back");
         }
+        new_t
+    })
+}
+
+/// Like `take_and_return_or_recover()`, using `T::placeholder()` to recover.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn take_and_return_or_placeholder<T, R, F>(mut_ref: &mut T, closure: F) -> R
+  where T: Placeholder, F: FnOnce(T) -> (T, R) {
+    take_and_return_or_recover(mut_ref, T::placeholder, closure)
+}
+
+/// Like `try_take()`, but recovers from a panic in the closure the same way `take_or_recover()` does.
+///
+/// # Important
+/// Will terminate the program (see `policy::DefaultPolicy`) if `recover` itself panics.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn try_take_or_recover<T, E, F, R>(mut_ref: &mut T, recover: R, closure: F) -> Result<(), E>
+  where F: FnOnce(T) -> Result<T, (T, E)>, R: FnOnce() -> T {
+    take_and_return_or_recover(mut_ref, recover, |t| match closure(t) {
+        Ok(new_t) => (new_t, Ok(())),
+        Err((old_t, err)) => (old_t, Err(err)),
+    })
+}
+
+/// Like `try_take_or_recover()`, using `T::placeholder()` to recover.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn try_take_or_placeholder<T, E, F>(mut_ref: &mut T, closure: F) -> Result<(), E>
+  where T: Placeholder, F: FnOnce(T) -> Result<T, (T, E)> {
+    try_take_or_recover(mut_ref, T::placeholder, closure)
+}
+
+/// Transitions the value at `place` with `take()` by matching it against a list of arms.
+///
+/// Values matching none of the arms are left unchanged, unless an `else` arm is given.
+/// With `return`, each arm produces a `(new_value, result)` pair as in `take_and_return()`,
+/// and unmatched values produce `Default::default()` as the result.
+///
+/// `place` must be a place expression, e.g. `self.state` or `*state_ref`.
+///
+/// # Important
+/// Will terminate the program (see `policy::DefaultPolicy`) if an arm panics.
+///
+/// # Example
+/// ```
+/// #[macro_use]
+/// extern crate take_mut;
+///
+/// #[derive(Debug, PartialEq)]
+/// enum State { Idle, Running(Vec<u8>), Done(usize) }
+///
+/// # fn main() {
+/// let mut state = State::Running(vec![1, 2]);
+/// take!(state, {
+///     State::Running(buf) if buf.len() > 1 => State::Done(buf.len()),
+///     State::Idle | State::Done(0) => State::Running(Vec::new()),
+/// });
+/// assert_eq!(state, State::Done(2));
+///
+/// let finished = take!(state, return {
+///     State::Done(n) => (State::Idle, Some(n)),
+/// });
+/// assert_eq!((state, finished), (State::Idle, Some(2)));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! take {
+    ($place:expr, return { $($($pat:pat)|+ $(if $guard:expr)? => $arm:expr),+ $(,)? } else $other:pat => $else_arm:expr) => {
+        $crate::take_and_return(&mut $place, |value| match value {
+            $($($pat)|+ $(if $guard)? => $arm,)+
+            $other => $else_arm,
+        })
+    };
+    ($place:expr, return { $($($pat:pat)|+ $(if $guard:expr)? => $arm:expr),+ $(,)? }) => {
+        $crate::take_and_return(&mut $place, |value| match value {
+            $($($pat)|+ $(if $guard)? => $arm,)+
+            #[allow(unreachable_patterns)]
+            other => (other, Default::default()),
+        })
+    };
+    ($place:expr, { $($($pat:pat)|+ $(if $guard:expr)? => $arm:expr),+ $(,)? } else $other:pat => $else_arm:expr) => {
+        $crate::take(&mut $place, |value| match value {
+            $($($pat)|+ $(if $guard)? => $arm,)+
+            $other => $else_arm,
+        })
+    };
+    ($place:expr, { $($($pat:pat)|+ $(if $guard:expr)? => $arm:expr),+ $(,)? }) => {
+        $crate::take(&mut $place, |value| match value {
+            $($($pat)|+ $(if $guard)? => $arm,)+
+            #[allow(unreachable_patterns)]
+            other => other,
+        })
+    };
+}
+
+#[test]
+fn it_tries() {
+    let mut foo = String::from("foo");
+    let res = try_take(&mut foo, |f| if f.is_empty() { Ok(f) } else { Err((f, "not empty")) });
+    assert_eq!(res, Err("not empty"));
+    assert_eq!(&foo, "foo");
+
+    let res: Result<(), ()> = try_take(&mut foo, |f| Ok(f + "bar"));
+    assert_eq!(res, Ok(()));
+    assert_eq!(&foo, "foobar");
+
+    let res = try_take_then(&mut foo, |f| f.parse::<u32>(), |f, n| f + &n.to_string());
+    assert!(res.is_err());
+    assert_eq!(&foo, "foobar");
+}
+
+#[test]
+fn it_takes_with_macro() {
+    #[derive(PartialEq, Eq, Debug)]
+    enum State {A(String), B(usize), C}
+    let mut state = State::A(String::from("foo"));
+    take!(state, { State::B(n) => State::A(n.to_string()) });
+    assert_eq!(state, State::A(String::from("foo")));
+    take!(state, { State::B(_) | State::C => State::C });
+    assert_eq!(state, State::A(String::from("foo")));
+    take!(state, { State::A(s) if s.is_empty() => State::C } else other => match other {
+        State::A(s) => State::B(s.len()),
+        other => other,
     });
+    assert_eq!(state, State::B(3));
+    let n: Option<usize> = take!(state, return { State::C => (State::C, Some(0)) });
+    assert_eq!((&state, n), (&State::B(3), None));
+    let n = take!(state, return { State::B(n) => (State::C, n) } else other => (other, 0));
+    assert_eq!((&state, n), (&State::C, 3));
+    take!(state, { State::B(_) | State::C => State::B(0) });
+    assert_eq!(state, State::B(0));
 }
 
+#[cfg(all(test, feature = "abort-handler"))]
+#[abort_handler]
+fn test_abort_handler() -> ! {
+    std::process::abort()
+}
 
 #[test]
 fn it_works() {
     #[derive(PartialEq, Eq, Debug)]
-    enum Foo {A, B};
+    enum Foo {A, B}
     impl Drop for Foo {
         fn drop(&mut self) {
             match *self {
@@ -53,9 +525,143 @@ fn it_works() {
         }
     }
     let mut foo = Foo::A;
-    take(&mut foo, |mut f| {
+    take(&mut foo, |f| {
        drop(f);
        Foo::B
     });
     assert_eq!(&foo, &Foo::B);
-}
\ No newline at end of file
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn it_recovers() {
+    let mut foo = String::from("foo");
+    let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        take_or_recover(&mut foo, || String::from("recovered"), |f| {
+            drop(f);
+            panic!("expected panic");
+        });
+    }));
+    assert!(res.is_err());
+    assert_eq!(&foo, "recovered");
+}
+
+#[test]
+fn it_returns() {
+    let mut foo = String::from("foo");
+    let len = take_and_return(&mut foo, |f| (f + "bar", 3));
+    assert_eq!(len, 3);
+    assert_eq!(&foo, "foobar");
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn it_recovers_with_placeholder() {
+    let mut foo = String::from("foo");
+    let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        take_or_placeholder(&mut foo, |_| panic!("expected panic"));
+    }));
+    assert!(res.is_err());
+    assert!(foo.is_empty());
+
+    let mut bar = Some(1);
+    let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        take_or_default(&mut bar, |_| panic!("expected panic"));
+    }));
+    assert!(res.is_err());
+    assert_eq!(bar, None);
+
+    let mut baz = Some(1);
+    let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        let _: Result<(), ()> = try_take_or_placeholder(&mut baz, |_| panic!("expected panic"));
+    }));
+    assert!(res.is_err());
+    assert_eq!(baz, None);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn it_returns_or_recovers() {
+    let mut foo = String::from("foobar");
+    let len = take_and_return_or_recover(&mut foo, String::new, |f| {
+        let len = f.len();
+        (f, len)
+    });
+    assert_eq!(len, 6);
+    assert_eq!(&foo, "foobar");
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn it_rolls_back_transactions() {
+    let mut foo = String::from("foo");
+    take_transactional_validated(&mut foo, |f| !f.is_empty(), |f| f + "bar");
+    assert_eq!(&foo, "foobar");
+    let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        take_transactional_validated(&mut foo, |f| !f.is_empty(), |_| String::new());
+    }));
+    assert_eq!(res.is_err(), cfg!(debug_assertions));
+    if cfg!(debug_assertions) {
+        assert_eq!(&foo, "foobar");
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn it_copies_without_guard() {
+    let mut foo = 1;
+    let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        take_copy(&mut foo, |_| panic!("expected panic"));
+    }));
+    assert!(res.is_err());
+    assert_eq!(foo, 1);
+    take_copy(&mut foo, |f| f + 1);
+    assert_eq!(foo, 2);
+}
+
+#[test]
+fn it_works_with_policy() {
+    fn hook() {}
+    let mut foo = 1;
+    take_with_policy(&mut foo, policy::Hook(hook), |f| f + 1);
+    let ret = take_and_return_with_policy(&mut foo, policy::Abort, |f| (f * 2, f));
+    assert_eq!(ret, 2);
+    assert_eq!(foo, 4);
+}
+
+#[test]
+fn it_terminates_on_panic() {
+    use std::{env, format};
+    use std::process::{Command, Stdio};
+    if env::var_os("TAKE_MUT_TERMINATE_CHILD").is_some() {
+        let mut foo = 0;
+        take(&mut foo, |_| panic!("expected panic"));
+        return;
+    }
+    let take_line = line!() - 3;
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "it_terminates_on_panic", "--test-threads=1"])
+        .env("TAKE_MUT_TERMINATE_CHILD", "1")
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+    let status = output.status;
+    if cfg!(feature = "diagnostics") {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("take_mut: panic while a value was taken"));
+        assert!(stderr.contains("panic: expected panic"));
+        assert!(stderr.contains(&format!("{}:{}", file!(), take_line)));
+    }
+    if cfg!(feature = "exit-on-panic") {
+        assert_eq!(status.code(), Some(101));
+    } else {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            // SIGABRT
+            assert_eq!(status.signal(), Some(6));
+        }
+        assert!(!status.success());
+    }
+}
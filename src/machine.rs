@@ -0,0 +1,261 @@
+//! A driver for state machines whose states transition by value.
+//!
+//! Implement `StateMachine` for an enum of states, then wrap it in a `Machine` and `feed()` it inputs. Each step
+//! runs through `take_and_return()`, or through `take_and_return_or_recover()` if a recovery state is configured.
+
+use core::any;
+use placeholder::Placeholder;
+use std::boxed::Box;
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(debug_assertions)]
+use std::collections::VecDeque;
+
+/// A state machine that transitions by value.
+pub trait StateMachine: Sized {
+    /// The input consumed by a step.
+    type Input;
+    /// The output produced by a step.
+    type Output;
+
+    /// Consumes the current state and an input, producing the next state and an output.
+    fn step(self, input: Self::Input) -> (Self, Self::Output);
+
+    /// A short name for the current state, passed to transition hooks and recorded in debug builds.
+    ///
+    /// Defaults to the name of the type. Enums will usually want to return the name of the variant.
+    fn label(&self) -> &'static str {
+        any::type_name::<Self>()
+    }
+}
+
+type TransitionHook<S> = Box<dyn FnMut(&'static str, &S) + Send>;
+
+/// How many state labels a `Machine` keeps in its history unless set with `Machine::with_history_len()`.
+pub const DEFAULT_HISTORY_LEN: usize = 64;
+
+/// Drives a `StateMachine`.
+///
+/// The recovery closure and transition hook must be `Send`, so a `Machine` can be moved to another thread or task
+/// whenever its state can.
+///
+/// # Example
+/// ```
+/// use take_mut::machine::{Machine, StateMachine};
+///
+/// enum Door {
+///     Open,
+///     Closed,
+/// }
+///
+/// impl StateMachine for Door {
+///     type Input = ();
+///     type Output = bool;
+///
+///     fn step(self, _: ()) -> (Self, bool) {
+///         match self {
+///             Door::Open => (Door::Closed, false),
+///             Door::Closed => (Door::Open, true),
+///         }
+///     }
+///
+///     fn label(&self) -> &'static str {
+///         match *self {
+///             Door::Open => "Open",
+///             Door::Closed => "Closed",
+///         }
+///     }
+/// }
+///
+/// let mut door = Machine::new(Door::Closed).with_recovery(|| Door::Closed);
+/// assert!(door.feed(()));
+/// assert!(!door.feed(()));
+/// assert!(matches!(door.state(), Door::Closed));
+/// ```
+pub struct Machine<S: StateMachine> {
+    state: S,
+    recovery: Option<Box<dyn Fn() -> S + Send>>,
+    hook: Option<TransitionHook<S>>,
+    #[cfg(debug_assertions)]
+    history: VecDeque<&'static str>,
+    #[cfg(debug_assertions)]
+    history_len: usize,
+}
+
+impl<S: StateMachine> Machine<S> {
+    /// Creates a machine in the given state.
+    ///
+    /// Without a recovery state, the process is terminated if a step panics.
+    pub fn new(state: S) -> Self {
+        #[cfg(debug_assertions)]
+        let mut history = VecDeque::new();
+        #[cfg(debug_assertions)]
+        history.push_back(state.label());
+        Machine {
+            #[cfg(debug_assertions)]
+            history,
+            #[cfg(debug_assertions)]
+            history_len: DEFAULT_HISTORY_LEN,
+            state,
+            recovery: None,
+            hook: None,
+        }
+    }
+
+    /// Sets the state to fall back to if a step panics. The panic then continues once the state is in place, and
+    /// after the transition into it has been recorded and passed to the transition hook.
+    pub fn with_recovery<R>(mut self, recovery: R) -> Self
+      where R: Fn() -> S + Send + 'static {
+        self.recovery = Some(Box::new(recovery));
+        self
+    }
+
+    /// Like `with_recovery()`, falling back to `S::placeholder()`.
+    pub fn with_placeholder_recovery(self) -> Self
+      where S: Placeholder + 'static {
+        self.with_recovery(S::placeholder)
+    }
+
+    /// Sets a hook called after every step with the label of the previous state and the new state.
+    ///
+    /// This includes a step that panicked and left the recovery state in place.
+    pub fn on_transition<H>(mut self, hook: H) -> Self
+      where H: FnMut(&'static str, &S) + Send + 'static {
+        self.hook = Some(Box::new(hook));
+        self
+    }
+
+    /// Sets how many of the most recent state labels are kept in the history, `0` disabling it.
+    ///
+    /// Defaults to `DEFAULT_HISTORY_LEN`. Has no effect in release builds, where no history is recorded.
+    pub fn with_history_len(self, len: usize) -> Self {
+        #[cfg(debug_assertions)]
+        {
+            let mut this = self;
+            this.history_len = len;
+            this.record_history(None);
+            this
+        }
+        #[cfg(not(debug_assertions))]
+        {
+            let _ = len;
+            self
+        }
+    }
+
+    /// Feeds an input to the machine, returning the output of the step.
+    #[track_caller]
+    pub fn feed(&mut self, input: S::Input) -> S::Output {
+        let from = self.state.label();
+        let output = match self.recovery {
+            Some(ref recovery) => {
+                let state = &mut self.state;
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    ::take_and_return_or_recover(state, recovery, |s| s.step(input))
+                }));
+                match result {
+                    Ok(output) => output,
+                    Err(err) => {
+                        self.transitioned(from);
+                        panic::resume_unwind(err);
+                    }
+                }
+            }
+            None => ::take_and_return(&mut self.state, |s| s.step(input)),
+        };
+        self.transitioned(from);
+        output
+    }
+
+    /// Records the transition from the state labelled `from` to the current one, and calls the hook.
+    fn transitioned(&mut self, from: &'static str) {
+        #[cfg(debug_assertions)]
+        self.record_history(Some(self.state.label()));
+        if let Some(ref mut hook) = self.hook {
+            hook(from, &self.state);
+        }
+    }
+
+    /// Returns the current state.
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// Returns the labels of the most recent states the machine has been in, oldest first.
+    ///
+    /// Only recorded in debug builds, up to `with_history_len()` of them; always empty otherwise.
+    pub fn history(&self) -> impl Iterator<Item = &'static str> + '_ {
+        #[cfg(debug_assertions)]
+        return self.history.iter().cloned();
+        #[cfg(not(debug_assertions))]
+        return [].iter().cloned();
+    }
+
+    /// Appends `label` to the history, dropping the oldest labels beyond `history_len`.
+    #[cfg(debug_assertions)]
+    fn record_history(&mut self, label: Option<&'static str>) {
+        self.history.extend(label);
+        while self.history.len() > self.history_len {
+            self.history.pop_front();
+        }
+    }
+
+    /// Consumes the machine, returning the current state.
+    pub fn into_inner(self) -> S {
+        self.state
+    }
+}
+
+
+#[test]
+fn machine_recovers_and_hooks() {
+    use std::panic;
+    use std::sync::{Arc, Mutex};
+    use std::{format, thread, vec::Vec};
+
+    #[derive(Debug, PartialEq)]
+    enum Counter {
+        Counting(u32),
+        Failed,
+    }
+
+    impl StateMachine for Counter {
+        type Input = u32;
+        type Output = u32;
+
+        fn step(self, input: u32) -> (Self, u32) {
+            match self {
+                Counter::Counting(n) if input > 0 => (Counter::Counting(n + input), n + input),
+                Counter::Counting(_) => panic!("expected panic"),
+                Counter::Failed => (Counter::Failed, 0),
+            }
+        }
+
+        fn label(&self) -> &'static str {
+            match *self {
+                Counter::Counting(_) => "Counting",
+                Counter::Failed => "Failed",
+            }
+        }
+    }
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let hook_seen = seen.clone();
+    let mut machine = Machine::new(Counter::Counting(0))
+        .with_history_len(3)
+        .with_recovery(|| Counter::Failed)
+        .on_transition(move |_, state: &Counter| hook_seen.lock().unwrap().push(format!("{:?}", state)));
+    // Machines can be moved to other threads or tasks.
+    let mut machine = thread::spawn(move || {
+        assert_eq!(machine.feed(2), 2);
+        machine
+    }).join().unwrap();
+    let res = panic::catch_unwind(panic::AssertUnwindSafe(|| machine.feed(0)));
+    assert!(res.is_err());
+    assert_eq!(*machine.state(), Counter::Failed);
+    assert_eq!(machine.feed(1), 0);
+    assert_eq!(*seen.lock().unwrap(), ["Counting(2)", "Failed", "Failed"]);
+    if cfg!(debug_assertions) {
+        // The initial "Counting" was dropped to keep the last 3.
+        assert_eq!(machine.history().collect::<Vec<_>>(), ["Counting", "Failed", "Failed"]);
+    }
+}
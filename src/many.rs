@@ -0,0 +1,252 @@
+use core::ptr;
+#[cfg(feature = "std")]
+use std::panic;
+
+use exit_on_panic::exit_on_panic;
+#[cfg(feature = "std")]
+use placeholder::Placeholder;
+
+/// Takes several values at once, under a single panic guard.
+///
+/// Implemented for tuples of up to 12 `&mut` references, and for arrays of `&mut T`.
+///
+/// # Example
+/// ```
+/// use take_mut::TakeMany;
+///
+/// let mut a = String::from("a");
+/// let mut b = 1;
+/// let mut c = vec![2];
+/// (&mut a, &mut b, &mut c).take_many(|(a, b, mut c)| {
+///     c.push(b);
+///     (a + "!", b + 1, c)
+/// });
+/// assert_eq!((a.as_str(), b, c), ("a!", 2, vec![2, 1]));
+///
+/// let (mut x, mut y) = (1, 2);
+/// [&mut x, &mut y].take_many(|[x, y]| [y, x]);
+/// assert_eq!((x, y), (2, 1));
+/// ```
+pub trait TakeMany {
+    /// The values behind the references, e.g. `(A, B)` for `(&mut A, &mut B)`.
+    type Values;
+
+    /// Like `take()`, for every value at once.
+    ///
+    /// # Important
+    /// Will terminate the program (see `policy::DefaultPolicy`) if the closure panics.
+    fn take_many<F>(self, closure: F)
+      where F: FnOnce(Self::Values) -> Self::Values;
+
+    /// Like `take_or_recover()`, for every value at once.
+    ///
+    /// If the closure panics, `recover` produces the values to write back, and the panic continues.
+    /// # Important
+    /// Will terminate the program (see `policy::DefaultPolicy`) if `recover` itself panics.
+    #[cfg(feature = "std")]
+    fn take_many_or_recover<R, F>(self, recover: R, closure: F)
+      where R: FnOnce() -> Self::Values, F: FnOnce(Self::Values) -> Self::Values;
+
+    /// Like `take_many_or_recover()`, using `Placeholder::placeholder()` for every value to recover.
+    #[cfg(feature = "std")]
+    #[track_caller]
+    fn take_many_or_placeholder<F>(self, closure: F)
+      where Self: Sized, Self::Values: Placeholder, F: FnOnce(Self::Values) -> Self::Values {
+        self.take_many_or_recover(Self::Values::placeholder, closure)
+    }
+}
+
+macro_rules! impl_take_many {
+    ($($t:ident $old:ident $new:ident),+) => {
+        impl<'a, $($t),+> TakeMany for ($(&'a mut $t,)+) {
+            type Values = ($($t,)+);
+
+            #[track_caller]
+            fn take_many<F>(self, closure: F)
+              where F: FnOnce(Self::Values) -> Self::Values {
+                let ($($old,)+) = self;
+                exit_on_panic(|| {
+                    unsafe {
+                        let ($($new,)+) = closure(($(ptr::read($old),)+));
+                        $(ptr::write($old, $new);)+
+                    }
+                });
+            }
+
+            #[cfg(feature = "std")]
+            #[track_caller]
+            fn take_many_or_recover<R, F>(self, recover: R, closure: F)
+              where R: FnOnce() -> Self::Values, F: FnOnce(Self::Values) -> Self::Values {
+                let ($($old,)+) = self;
+                unsafe {
+                    let old_values = ($(ptr::read($old),)+);
+                    match panic::catch_unwind(panic::AssertUnwindSafe(|| closure(old_values))) {
+                        Ok(($($new,)+)) => {
+                            $(ptr::write($old, $new);)+
+                        }
+                        Err(err) => {
+                            let ($($new,)+) = exit_on_panic(recover);
+                            $(ptr::write($old, $new);)+
+                            panic::resume_unwind(err);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl_take_many!(A a0 a1);
+impl_take_many!(A a0 a1, B b0 b1);
+impl_take_many!(A a0 a1, B b0 b1, C c0 c1);
+impl_take_many!(A a0 a1, B b0 b1, C c0 c1, D d0 d1);
+impl_take_many!(A a0 a1, B b0 b1, C c0 c1, D d0 d1, E e0 e1);
+impl_take_many!(A a0 a1, B b0 b1, C c0 c1, D d0 d1, E e0 e1, F6 f0 f1);
+impl_take_many!(A a0 a1, B b0 b1, C c0 c1, D d0 d1, E e0 e1, F6 f0 f1, G g0 g1);
+impl_take_many!(A a0 a1, B b0 b1, C c0 c1, D d0 d1, E e0 e1, F6 f0 f1, G g0 g1, H h0 h1);
+impl_take_many!(A a0 a1, B b0 b1, C c0 c1, D d0 d1, E e0 e1, F6 f0 f1, G g0 g1, H h0 h1, I i0 i1);
+impl_take_many!(A a0 a1, B b0 b1, C c0 c1, D d0 d1, E e0 e1, F6 f0 f1, G g0 g1, H h0 h1, I i0 i1, J j0 j1);
+impl_take_many!(A a0 a1, B b0 b1, C c0 c1, D d0 d1, E e0 e1, F6 f0 f1, G g0 g1, H h0 h1, I i0 i1, J j0 j1, K k0 k1);
+impl_take_many!(A a0 a1, B b0 b1, C c0 c1, D d0 d1, E e0 e1, F6 f0 f1, G g0 g1, H h0 h1, I i0 i1, J j0 j1, K k0 k1, L l0 l1);
+
+impl<T, const N: usize> TakeMany for [&mut T; N] {
+    type Values = [T; N];
+
+    #[track_caller]
+    fn take_many<F>(self, closure: F)
+      where F: FnOnce(Self::Values) -> Self::Values {
+        exit_on_panic(|| {
+            unsafe {
+                let new_values = closure(::core::array::from_fn(|i| ptr::read(&*self[i])));
+                for (slot, new_t) in IntoIterator::into_iter(self).zip(new_values) {
+                    ptr::write(slot, new_t);
+                }
+            }
+        });
+    }
+
+    #[cfg(feature = "std")]
+    #[track_caller]
+    fn take_many_or_recover<R, F>(self, recover: R, closure: F)
+      where R: FnOnce() -> Self::Values, F: FnOnce(Self::Values) -> Self::Values {
+        unsafe {
+            let old_values = ::core::array::from_fn(|i| ptr::read(&*self[i]));
+            let (new_values, err) = match panic::catch_unwind(panic::AssertUnwindSafe(|| closure(old_values))) {
+                Ok(new_values) => (new_values, None),
+                Err(err) => (exit_on_panic(recover), Some(err)),
+            };
+            for (slot, new_t) in IntoIterator::into_iter(self).zip(new_values) {
+                ptr::write(slot, new_t);
+            }
+            if let Some(err) = err {
+                panic::resume_unwind(err);
+            }
+        }
+    }
+}
+
+/// Like `take()`, for two values at once.
+///
+/// # Important
+/// Will terminate the program (see `policy::DefaultPolicy`) if the closure panics.
+///
+/// # Example
+/// ```
+/// let mut buffer = vec![1, 2];
+/// let mut sink: Vec<i32> = Vec::new();
+/// take_mut::take2(&mut buffer, &mut sink, |buffer, mut sink| {
+///     sink.extend(buffer);
+///     (Vec::new(), sink)
+/// });
+/// assert!(buffer.is_empty());
+/// assert_eq!(sink, [1, 2]);
+/// ```
+#[track_caller]
+pub fn take2<A, B, F>(a: &mut A, b: &mut B, closure: F)
+  where F: FnOnce(A, B) -> (A, B) {
+    (a, b).take_many(|(a, b)| closure(a, b))
+}
+
+/// Like `take()`, for three values at once.
+///
+/// # Important
+/// Will terminate the program (see `policy::DefaultPolicy`) if the closure panics.
+#[track_caller]
+pub fn take3<A, B, C, F>(a: &mut A, b: &mut B, c: &mut C, closure: F)
+  where F: FnOnce(A, B, C) -> (A, B, C) {
+    (a, b, c).take_many(|(a, b, c)| closure(a, b, c))
+}
+
+/// Like `take2()`, but each value is recovered by its own closure if `closure` panics.
+///
+/// # Important
+/// Will terminate the program (see `policy::DefaultPolicy`) if a recovery closure panics.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn take2_or_recover<A, B, RA, RB, F>(a: &mut A, b: &mut B, recover_a: RA, recover_b: RB, closure: F)
+  where RA: FnOnce() -> A, RB: FnOnce() -> B, F: FnOnce(A, B) -> (A, B) {
+    (a, b).take_many_or_recover(|| (recover_a(), recover_b()), |(a, b)| closure(a, b))
+}
+
+/// Like `take3()`, but each value is recovered by its own closure if `closure` panics.
+///
+/// # Important
+/// Will terminate the program (see `policy::DefaultPolicy`) if a recovery closure panics.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn take3_or_recover<A, B, C, RA, RB, RC, F>(a: &mut A, b: &mut B, c: &mut C, recover_a: RA, recover_b: RB, recover_c: RC, closure: F)
+  where RA: FnOnce() -> A, RB: FnOnce() -> B, RC: FnOnce() -> C, F: FnOnce(A, B, C) -> (A, B, C) {
+    (a, b, c).take_many_or_recover(|| (recover_a(), recover_b(), recover_c()), |(a, b, c)| closure(a, b, c))
+}
+
+/// Like `take2_or_recover()`, using `Placeholder::placeholder()` to recover each value.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn take2_or_placeholder<A, B, F>(a: &mut A, b: &mut B, closure: F)
+  where A: Placeholder, B: Placeholder, F: FnOnce(A, B) -> (A, B) {
+    (a, b).take_many_or_placeholder(|(a, b)| closure(a, b))
+}
+
+/// Like `take3_or_recover()`, using `Placeholder::placeholder()` to recover each value.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn take3_or_placeholder<A, B, C, F>(a: &mut A, b: &mut B, c: &mut C, closure: F)
+  where A: Placeholder, B: Placeholder, C: Placeholder, F: FnOnce(A, B, C) -> (A, B, C) {
+    (a, b, c).take_many_or_placeholder(|(a, b, c)| closure(a, b, c))
+}
+
+
+#[test]
+fn take_many_swaps() {
+    let (mut a, mut b, mut c) = (1, 2, 3);
+    take3(&mut a, &mut b, &mut c, |a, b, c| (c, a, b));
+    assert_eq!((a, b, c), (3, 1, 2));
+
+    let mut arr = [0; 4];
+    {
+        let [w, x, y, z] = &mut arr;
+        [w, x, y, z].take_many(|[w, x, y, z]| [w + 1, x + 2, y + 3, z + 4]);
+    }
+    assert_eq!(arr, [1, 2, 3, 4]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn take_many_recovers_each_slot() {
+    use std::string::String;
+    let mut a = String::from("a");
+    let mut b = 1;
+    let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        take2_or_recover(&mut a, &mut b, || String::from("recovered"), || 0, |_, _| panic!("expected panic"));
+    }));
+    assert!(res.is_err());
+    assert_eq!((a.as_str(), b), ("recovered", 0));
+
+    let mut arr = [Some(1), Some(2)];
+    let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        let [x, y] = &mut arr;
+        [x, y].take_many_or_placeholder(|_| panic!("expected panic"));
+    }));
+    assert!(res.is_err());
+    assert_eq!(arr, [None, None]);
+}
@@ -0,0 +1,130 @@
+//! Cheap values to leave behind when a `take()` closure panics.
+
+/// A type with a cheap value that can stand in for a lost `T`, used by `take_or_placeholder()`.
+///
+/// Unlike `Default`, implementing `Placeholder` makes no claim that the value is a sensible default. It should be
+/// cheap to create and free of side effects, as it's only meant to keep a `&mut T` valid after a panic.
+///
+/// With the `macros` feature, `#[derive(Placeholder)]` implements it for structs and enums, taking per-field values
+/// from `#[placeholder = literal]` or `#[placeholder(expr)]` attributes and the enum variant from a `#[placeholder]` marker.
+pub trait Placeholder {
+    /// Returns the placeholder value.
+    fn placeholder() -> Self;
+}
+
+macro_rules! impl_placeholder {
+    ($($t:ty => $e:expr),* $(,)*) => {
+        $(
+            impl Placeholder for $t {
+                fn placeholder() -> Self {
+                    $e
+                }
+            }
+        )*
+    }
+}
+
+impl_placeholder! {
+    () => (),
+    bool => false,
+    char => '\0',
+    u8 => 0, u16 => 0, u32 => 0, u64 => 0, u128 => 0, usize => 0,
+    i8 => 0, i16 => 0, i32 => 0, i64 => 0, i128 => 0, isize => 0,
+    f32 => 0.0, f64 => 0.0,
+}
+
+impl<T> Placeholder for Option<T> {
+    fn placeholder() -> Self {
+        None
+    }
+}
+
+impl<T: Placeholder, const N: usize> Placeholder for [T; N] {
+    fn placeholder() -> Self {
+        ::core::array::from_fn(|_| T::placeholder())
+    }
+}
+
+macro_rules! impl_placeholder_tuple {
+    ($($t:ident),+) => {
+        impl<$($t: Placeholder),+> Placeholder for ($($t,)+) {
+            fn placeholder() -> Self {
+                ($($t::placeholder(),)+)
+            }
+        }
+    }
+}
+
+impl_placeholder_tuple!(A);
+impl_placeholder_tuple!(A, B);
+impl_placeholder_tuple!(A, B, C);
+impl_placeholder_tuple!(A, B, C, D);
+impl_placeholder_tuple!(A, B, C, D, E);
+impl_placeholder_tuple!(A, B, C, D, E, F);
+impl_placeholder_tuple!(A, B, C, D, E, F, G);
+impl_placeholder_tuple!(A, B, C, D, E, F, G, H);
+impl_placeholder_tuple!(A, B, C, D, E, F, G, H, I);
+impl_placeholder_tuple!(A, B, C, D, E, F, G, H, I, J);
+impl_placeholder_tuple!(A, B, C, D, E, F, G, H, I, J, K);
+impl_placeholder_tuple!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+#[cfg(feature = "std")]
+mod std_impls {
+    use super::Placeholder;
+    use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, LinkedList, VecDeque};
+    use std::hash::{BuildHasher, Hash};
+    use std::string::String;
+    use std::vec::Vec;
+
+    impl_placeholder! {
+        String => String::new(),
+    }
+
+    impl<T> Placeholder for Vec<T> {
+        fn placeholder() -> Self {
+            Vec::new()
+        }
+    }
+
+    impl<T> Placeholder for VecDeque<T> {
+        fn placeholder() -> Self {
+            VecDeque::new()
+        }
+    }
+
+    impl<T> Placeholder for LinkedList<T> {
+        fn placeholder() -> Self {
+            LinkedList::new()
+        }
+    }
+
+    impl<T: Ord> Placeholder for BinaryHeap<T> {
+        fn placeholder() -> Self {
+            BinaryHeap::new()
+        }
+    }
+
+    impl<K: Ord, V> Placeholder for BTreeMap<K, V> {
+        fn placeholder() -> Self {
+            BTreeMap::new()
+        }
+    }
+
+    impl<T: Ord> Placeholder for BTreeSet<T> {
+        fn placeholder() -> Self {
+            BTreeSet::new()
+        }
+    }
+
+    impl<K: Eq + Hash, V, S: BuildHasher + Default> Placeholder for HashMap<K, V, S> {
+        fn placeholder() -> Self {
+            HashMap::default()
+        }
+    }
+
+    impl<T: Eq + Hash, S: BuildHasher + Default> Placeholder for HashSet<T, S> {
+        fn placeholder() -> Self {
+            HashSet::default()
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! A slot that becomes poisoned, rather than terminating the process, when a `take()` closure panics.
+
+use core::fmt;
+use core::mem::{ManuallyDrop, MaybeUninit};
+use core::ptr;
+
+/// Holds a `T` that can be taken by value, and is left empty and poisoned if the closure panics.
+///
+/// This isolates a failure to the one slot it happened in. Callers can check `is_poisoned()`, put a new value in
+/// with `recover_with()`, or give up on the slot.
+///
+/// # Example
+/// ```
+/// use std::panic;
+/// use take_mut::Poisonable;
+///
+/// let mut slot = Poisonable::new(String::from("foo"));
+/// slot.take(|s| s + "bar").unwrap();
+/// assert_eq!(slot.get().unwrap(), "foobar");
+///
+/// let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+///     slot.take(|_| panic!("oops")).unwrap();
+/// }));
+/// assert!(res.is_err());
+/// assert!(slot.is_poisoned());
+///
+/// slot.recover_with(String::new);
+/// assert_eq!(slot.into_inner().unwrap(), "");
+/// ```
+pub struct Poisonable<T> {
+    value: MaybeUninit<T>,
+    poisoned: bool,
+}
+
+/// The error returned when accessing a `Poisonable` that has been poisoned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoisonError {
+    _priv: (),
+}
+
+impl<T> Poisonable<T> {
+    /// Creates a new, unpoisoned slot holding `value`.
+    pub fn new(value: T) -> Self {
+        Poisonable {
+            value: MaybeUninit::new(value),
+            poisoned: false,
+        }
+    }
+
+    /// Allows use of the value as though it was owned, as long as a `T` is returned by the closure.
+    ///
+    /// If the closure panics, the slot is left poisoned and the panic continues.
+    /// Returns an error without calling the closure if the slot is already poisoned.
+    pub fn take<F>(&mut self, closure: F) -> Result<(), PoisonError>
+      where F: FnOnce(T) -> T {
+        self.take_and_return(|t| (closure(t), ()))
+    }
+
+    /// Like `take()`, but the closure also returns a value of type `R`, which is passed back to the caller.
+    pub fn take_and_return<R, F>(&mut self, closure: F) -> Result<R, PoisonError>
+      where F: FnOnce(T) -> (T, R) {
+        if self.poisoned {
+            return Err(PoisonError { _priv: () });
+        }
+        // Stays set if the closure panics, which keeps the moved-out value from being dropped again.
+        self.poisoned = true;
+        let ret = unsafe {
+            let old_t = ptr::read(self.value.as_ptr());
+            let (new_t, ret) = closure(old_t);
+            ptr::write(self.value.as_mut_ptr(), new_t);
+            ret
+        };
+        self.poisoned = false;
+        Ok(ret)
+    }
+
+    /// Returns `true` if a closure panicked while the value was taken, and the slot hasn't been recovered since.
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    /// If the slot is poisoned, fills it with the result of `recover` and clears the poison.
+    ///
+    /// Does nothing if the slot isn't poisoned.
+    pub fn recover_with<F>(&mut self, recover: F)
+      where F: FnOnce() -> T {
+        if self.poisoned {
+            self.value = MaybeUninit::new(recover());
+            self.poisoned = false;
+        }
+    }
+
+    /// Returns a reference to the value, or an error if the slot is poisoned.
+    pub fn get(&self) -> Result<&T, PoisonError> {
+        if self.poisoned {
+            Err(PoisonError { _priv: () })
+        } else {
+            Ok(unsafe { &*self.value.as_ptr() })
+        }
+    }
+
+    /// Returns a mutable reference to the value, or an error if the slot is poisoned.
+    pub fn get_mut(&mut self) -> Result<&mut T, PoisonError> {
+        if self.poisoned {
+            Err(PoisonError { _priv: () })
+        } else {
+            Ok(unsafe { &mut *self.value.as_mut_ptr() })
+        }
+    }
+
+    /// Consumes the slot, returning the value, or an error if the slot is poisoned.
+    pub fn into_inner(self) -> Result<T, PoisonError> {
+        let this = ManuallyDrop::new(self);
+        if this.poisoned {
+            Err(PoisonError { _priv: () })
+        } else {
+            Ok(unsafe { ptr::read(this.value.as_ptr()) })
+        }
+    }
+}
+
+impl<T> From<T> for Poisonable<T> {
+    fn from(value: T) -> Self {
+        Poisonable::new(value)
+    }
+}
+
+impl<T> Drop for Poisonable<T> {
+    fn drop(&mut self) {
+        if !self.poisoned {
+            unsafe {
+                ptr::drop_in_place(self.value.as_mut_ptr());
+            }
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Poisonable<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.get() {
+            Ok(value) => f.debug_tuple("Poisonable").field(value).finish(),
+            Err(_) => f.write_str("Poisonable(<poisoned>)"),
+        }
+    }
+}
+
+impl fmt::Display for PoisonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("value was lost to a panic while taken")
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for PoisonError {}
+
+
+#[test]
+fn poisonable_poisons_on_panic() {
+    use std::panic;
+    use std::rc::Rc;
+    let counter = Rc::new(());
+    let mut slot = Poisonable::new(counter.clone());
+    let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        slot.take(|_| panic!("expected panic")).unwrap();
+    }));
+    assert!(res.is_err());
+    assert!(slot.is_poisoned());
+    assert!(slot.get().is_err());
+    assert_eq!(slot.take(|c| c), Err(PoisonError { _priv: () }));
+    // The taken value was dropped by the unwinding closure, not leaked or dropped twice.
+    assert_eq!(Rc::strong_count(&counter), 1);
+
+    slot.recover_with(|| counter.clone());
+    assert!(!slot.is_poisoned());
+    let len = slot.take_and_return(|c| (c, 2)).unwrap();
+    assert_eq!(len, 2);
+    drop(slot);
+    assert_eq!(Rc::strong_count(&counter), 1);
+}
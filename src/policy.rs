@@ -0,0 +1,110 @@
+//! What to do when a panic would leave a `&mut T` without a valid value.
+//!
+//! `take()` and friends can't unwind past a `&mut T` whose value has been taken, so they terminate the process
+//! instead. A `PanicPolicy` decides how that happens. `take_with_policy()` picks one per call; everything else
+//! uses `DefaultPolicy`, which aborts unless the `exit-on-panic` Cargo feature is enabled.
+
+#[cfg(feature = "std")]
+use std::process;
+
+/// Decides how the process is terminated when a panic can't be allowed to unwind.
+pub trait PanicPolicy {
+    /// Terminates the process. Must not return or unwind.
+    fn terminate(&self) -> !;
+}
+
+impl<P: PanicPolicy + ?Sized> PanicPolicy for &P {
+    fn terminate(&self) -> ! {
+        (**self).terminate()
+    }
+}
+
+/// Aborts the process.
+///
+/// Uses `std::process::abort()` if the `std` feature is enabled. Otherwise calls the function registered with
+/// `#[abort_handler]` if the `abort-handler` feature is enabled, or panics while already panicking, which makes
+/// the Rust runtime abort.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Abort;
+
+impl PanicPolicy for Abort {
+    fn terminate(&self) -> ! {
+        abort()
+    }
+}
+
+/// Terminates the process with `std::process::exit()`, using the given status code.
+///
+/// Note that `exit()` runs `atexit` handlers and flushes stdio while other threads keep running.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Exit(pub i32);
+
+#[cfg(feature = "std")]
+impl PanicPolicy for Exit {
+    fn terminate(&self) -> ! {
+        process::exit(self.0)
+    }
+}
+
+/// Calls the given function, then aborts the process like `Abort`.
+///
+/// Useful for logging or flushing state before going down.
+#[derive(Debug, Clone, Copy)]
+pub struct Hook(pub fn());
+
+impl PanicPolicy for Hook {
+    fn terminate(&self) -> ! {
+        (self.0)();
+        abort()
+    }
+}
+
+/// The policy used by `take()` and every other function not taking an explicit `PanicPolicy`.
+///
+/// Behaves like `Abort`, or like `Exit(101)` if the `exit-on-panic` Cargo feature is enabled.
+///
+/// Aborting is the default because it's the only option that stops other threads immediately.
+/// While `exit()` runs, another thread could still observe the `&mut T` that was left without a valid value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DefaultPolicy;
+
+impl PanicPolicy for DefaultPolicy {
+    #[cfg(not(feature = "exit-on-panic"))]
+    fn terminate(&self) -> ! {
+        Abort.terminate()
+    }
+
+    #[cfg(feature = "exit-on-panic")]
+    fn terminate(&self) -> ! {
+        Exit(101).terminate()
+    }
+}
+
+#[cfg(feature = "std")]
+fn abort() -> ! {
+    process::abort()
+}
+
+#[cfg(all(not(feature = "std"), feature = "abort-handler"))]
+fn abort() -> ! {
+    extern "Rust" {
+        fn __take_mut_abort_handler() -> !;
+    }
+    unsafe { __take_mut_abort_handler() }
+}
+
+#[cfg(all(not(feature = "std"), not(feature = "abort-handler")))]
+fn abort() -> ! {
+    struct PanicOnDrop;
+
+    impl Drop for PanicOnDrop {
+        fn drop(&mut self) {
+            panic!("take_mut: aborting");
+        }
+    }
+
+    // Panicking while `PanicOnDrop` unwinds is a double panic, which the runtime turns into an abort.
+    let _panic_on_drop = PanicOnDrop;
+    panic!("take_mut: aborting");
+}
@@ -0,0 +1,132 @@
+//! Transform the contents of a `RefCell<T>`, or of a `RefMut<T>`, by value.
+//!
+//! The cell stays mutably borrowed while the closure runs, so the closure can't observe the taken value through
+//! the cell: trying to borrow it panics. The borrow is released on every path, including when a panic is recovered
+//! from.
+
+use core::cell::{RefCell, RefMut};
+
+#[cfg(feature = "std")]
+use placeholder::Placeholder;
+
+/// Like the crate-level `take()`, for the contents of a `RefCell<T>`.
+///
+/// # Panics
+/// Panics if the cell is already borrowed.
+/// # Important
+/// Will terminate the program (see `policy::DefaultPolicy`) if the closure panics.
+///
+/// # Example
+/// ```
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+///
+/// let state = Rc::new(RefCell::new(String::from("foo")));
+/// take_mut::refcell::take(&state, |s| s + "bar");
+/// assert_eq!(*state.borrow(), "foobar");
+/// ```
+#[track_caller]
+pub fn take<T, F>(cell: &RefCell<T>, closure: F)
+  where F: FnOnce(T) -> T {
+    ::take(&mut *cell.borrow_mut(), closure)
+}
+
+/// Like the crate-level `take_or_recover()`, for the contents of a `RefCell<T>`.
+///
+/// # Panics
+/// Panics if the cell is already borrowed.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn take_or_recover<T, F, R>(cell: &RefCell<T>, recover: R, closure: F)
+  where F: FnOnce(T) -> T, R: FnOnce() -> T {
+    ::take_or_recover(&mut *cell.borrow_mut(), recover, closure)
+}
+
+/// Like the crate-level `take_or_default()`, for the contents of a `RefCell<T>`.
+///
+/// # Panics
+/// Panics if the cell is already borrowed.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn take_or_default<T, F>(cell: &RefCell<T>, closure: F)
+  where T: Default, F: FnOnce(T) -> T {
+    ::take_or_default(&mut *cell.borrow_mut(), closure)
+}
+
+/// Like the crate-level `take_or_placeholder()`, for the contents of a `RefCell<T>`.
+///
+/// # Panics
+/// Panics if the cell is already borrowed.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn take_or_placeholder<T, F>(cell: &RefCell<T>, closure: F)
+  where T: Placeholder, F: FnOnce(T) -> T {
+    ::take_or_placeholder(&mut *cell.borrow_mut(), closure)
+}
+
+/// Like the crate-level `take()`, for the value behind a `RefMut<T>`.
+///
+/// # Important
+/// Will terminate the program (see `policy::DefaultPolicy`) if the closure panics.
+#[track_caller]
+pub fn take_ref_mut<T, F>(ref_mut: &mut RefMut<T>, closure: F)
+  where F: FnOnce(T) -> T {
+    ::take(&mut **ref_mut, closure)
+}
+
+/// Like the crate-level `take_or_recover()`, for the value behind a `RefMut<T>`.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn take_ref_mut_or_recover<T, F, R>(ref_mut: &mut RefMut<T>, recover: R, closure: F)
+  where F: FnOnce(T) -> T, R: FnOnce() -> T {
+    ::take_or_recover(&mut **ref_mut, recover, closure)
+}
+
+/// Like the crate-level `take_or_default()`, for the value behind a `RefMut<T>`.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn take_ref_mut_or_default<T, F>(ref_mut: &mut RefMut<T>, closure: F)
+  where T: Default, F: FnOnce(T) -> T {
+    ::take_or_default(&mut **ref_mut, closure)
+}
+
+/// Like the crate-level `take_or_placeholder()`, for the value behind a `RefMut<T>`.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn take_ref_mut_or_placeholder<T, F>(ref_mut: &mut RefMut<T>, closure: F)
+  where T: Placeholder, F: FnOnce(T) -> T {
+    ::take_or_placeholder(&mut **ref_mut, closure)
+}
+
+
+#[test]
+fn refcell_take() {
+    let cell = RefCell::new(1);
+    take(&cell, |x| x + 1);
+    {
+        let mut ref_mut = cell.borrow_mut();
+        take_ref_mut(&mut ref_mut, |x| x * 3);
+    }
+    assert_eq!(cell.into_inner(), 6);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn refcell_releases_borrow_on_recover() {
+    use std::panic;
+    let cell = RefCell::new(1);
+    let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        take_or_recover(&cell, || 7, |_| panic!("expected panic"));
+    }));
+    assert!(res.is_err());
+    assert_eq!(*cell.borrow(), 7);
+
+    let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        take_or_default(&cell, |x| {
+            let _ = cell.borrow();
+            x
+        });
+    }));
+    assert!(res.is_err());
+    assert_eq!(*cell.borrow_mut(), 0);
+}
@@ -0,0 +1,172 @@
+//! Take several values at once within a scope.
+//!
+//! Within `scope()`, each `Scope::take()` hands out an owned `T` together with a `Hole` that must be filled
+//! with a new `T` before the scope ends. This allows moving values between several `&mut T` at the same time.
+//!
+//! A `Hole` that is dropped without being filled is refilled from its recovery closure, if it was created by
+//! `Scope::take_or_recover()` or `Scope::take_or_placeholder()`. Otherwise the process is terminated, as with `take()`.
+//!
+//! # Example
+//! ```
+//! let mut a = String::from("a");
+//! let mut b = String::from("b");
+//! take_mut::scope(|scope| {
+//!     let (a_val, a_hole) = scope.take(&mut a);
+//!     let (b_val, b_hole) = scope.take(&mut b);
+//!     a_hole.fill(b_val);
+//!     b_hole.fill(a_val);
+//! });
+//! assert_eq!(a, "b");
+//! assert_eq!(b, "a");
+//! ```
+
+use core::cell::Cell;
+use core::marker::PhantomData;
+use core::ptr;
+
+use exit_on_panic::{exit_on_panic, AbortOnUnwind};
+use placeholder::Placeholder;
+#[cfg(test)]
+use std::{string::String, vec, vec::Vec};
+
+/// A scope within which a `T` can be taken from a `&mut T`, as long as the `&mut T` outlives the scope.
+///
+/// Created by `scope()`.
+pub struct Scope<'s> {
+    active_holes: Cell<usize>,
+    marker: PhantomData<Cell<&'s mut ()>>,
+}
+
+/// Runs `f` with a new `Scope`.
+///
+/// # Important
+/// Will terminate the program (see `policy::DefaultPolicy`) if any `Hole` taken within the scope is left empty without a
+/// recovery closure, for example because `f` panicked or the `Hole` was leaked.
+pub fn scope<'s, F, R>(f: F) -> R
+  where F: FnOnce(&Scope<'s>) -> R {
+    let this = Scope { active_holes: Cell::new(0), marker: PhantomData };
+    f(&this)
+}
+
+impl<'s> Scope<'s> {
+    /// Takes a `T` from `mut_ref`, leaving a `Hole` behind.
+    ///
+    /// # Important
+    /// Will terminate the program (see `policy::DefaultPolicy`) if the `Hole` is dropped without being filled.
+    pub fn take<'c, 'm: 's, T: 'm>(&'c self, mut_ref: &'m mut T) -> (T, Hole<'c, 'm, T>) {
+        self.take_hole(mut_ref, None)
+    }
+
+    /// Takes a `T` from `mut_ref`, leaving a `Hole` behind.
+    ///
+    /// If the `Hole` is dropped without being filled, including during a panic, it is refilled with the result of
+    /// `recovery`.
+    /// # Important
+    /// Will terminate the program (see `policy::DefaultPolicy`) if `recovery` panics.
+    pub fn take_or_recover<'c, 'm: 's, T: 'm, F: FnOnce() -> T>(&'c self, mut_ref: &'m mut T, recovery: F) -> (T, Hole<'c, 'm, T, F>) {
+        self.take_hole(mut_ref, Some(recovery))
+    }
+
+    /// Like `take_or_recover()`, using `T::placeholder()` as the recovery closure.
+    pub fn take_or_placeholder<'c, 'm: 's, T: 'm + Placeholder>(&'c self, mut_ref: &'m mut T) -> (T, Hole<'c, 'm, T>) {
+        self.take_hole(mut_ref, Some(T::placeholder as fn() -> T))
+    }
+
+    fn take_hole<'c, 'm: 's, T: 'm, F: FnOnce() -> T>(&'c self, mut_ref: &'m mut T, recovery: Option<F>) -> (T, Hole<'c, 'm, T, F>) {
+        let t = unsafe { ptr::read(mut_ref) };
+        self.active_holes.set(self.active_holes.get() + 1);
+        let hole = Hole {
+            active_holes: &self.active_holes,
+            hole: mut_ref,
+            recovery,
+        };
+        (t, hole)
+    }
+}
+
+impl<'s> Drop for Scope<'s> {
+    fn drop(&mut self) {
+        if self.active_holes.get() != 0 {
+            // A `Hole` was leaked while empty, so there's no valid `T` behind it.
+            let _guard = AbortOnUnwind::new();
+        }
+    }
+}
+
+/// A `&mut T` whose value has been taken by a `Scope`, and which must be filled again.
+pub struct Hole<'c, 'm, T: 'm, F: FnOnce() -> T = fn() -> T> {
+    active_holes: &'c Cell<usize>,
+    hole: &'m mut T,
+    recovery: Option<F>,
+}
+
+impl<'c, 'm, T: 'm, F: FnOnce() -> T> Hole<'c, 'm, T, F> {
+    /// Fills the `Hole` with `t`.
+    pub fn fill(mut self, t: T) {
+        unsafe {
+            ptr::write(self.hole as *mut T, t);
+        }
+        self.recovery = None;
+        self.active_holes.set(self.active_holes.get() - 1);
+        ::core::mem::forget(self);
+    }
+}
+
+impl<'c, 'm, T: 'm, F: FnOnce() -> T> Drop for Hole<'c, 'm, T, F> {
+    fn drop(&mut self) {
+        match self.recovery.take() {
+            Some(recovery) => {
+                let t = exit_on_panic(recovery);
+                unsafe {
+                    ptr::write(self.hole as *mut T, t);
+                }
+                self.active_holes.set(self.active_holes.get() - 1);
+            }
+            None => {
+                let _guard = AbortOnUnwind::new();
+            }
+        }
+    }
+}
+
+
+#[test]
+fn scope_moves_between_holes() {
+    let mut a = vec![1, 2];
+    let mut b = vec![3];
+    scope(|scope| {
+        let (mut a_val, a_hole) = scope.take(&mut a);
+        let (b_val, b_hole) = scope.take(&mut b);
+        a_val.extend(b_val);
+        b_hole.fill(a_val);
+        a_hole.fill(Vec::new());
+    });
+    assert!(a.is_empty());
+    assert_eq!(b, [1, 2, 3]);
+}
+
+#[test]
+fn scope_recovers_holes_on_panic() {
+    use std::panic;
+    let mut a = String::from("a");
+    let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        scope(|scope| {
+            let (a_val, _a_hole) = scope.take_or_recover(&mut a, || String::from("recovered"));
+            drop(a_val);
+            panic!("expected panic");
+        })
+    }));
+    assert!(res.is_err());
+    assert_eq!(&a, "recovered");
+}
+
+#[test]
+fn scope_refills_placeholder_holes() {
+    let mut a = Some(String::from("a"));
+    scope(|scope| {
+        let (a_val, a_hole) = scope.take_or_placeholder(&mut a);
+        assert_eq!(a_val.as_deref(), Some("a"));
+        drop(a_hole);
+    });
+    assert_eq!(a, None);
+}
@@ -0,0 +1,106 @@
+//! Helpers for state machines written by hand in `Future::poll()`, `Stream::poll_next()` or `Iterator::next()`.
+//!
+//! Each helper takes the current state by value and puts back the state returned by the closure, along with the
+//! `Poll` or `Option` that the method should return. As with `take()`, the process is terminated if the closure
+//! panics.
+
+use core::pin::Pin;
+use core::task::Poll;
+
+/// Transitions a pinned, `Unpin` state, returning the `Poll` produced by the closure.
+///
+/// # Example
+/// ```
+/// use std::future::Future;
+/// use std::pin::Pin;
+/// use std::task::{Context, Poll};
+///
+/// enum Countdown {
+///     Running(u32),
+///     Done,
+/// }
+///
+/// impl Future for Countdown {
+///     type Output = ();
+///
+///     fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+///         take_mut::state::transition(self, |state| match state {
+///             Countdown::Running(0) => (Countdown::Done, Poll::Ready(())),
+///             Countdown::Running(n) => {
+///                 cx.waker().wake_by_ref();
+///                 (Countdown::Running(n - 1), Poll::Pending)
+///             }
+///             Countdown::Done => panic!("polled after completion"),
+///         })
+///     }
+/// }
+/// ```
+#[track_caller]
+pub fn transition<S, R, F>(state: Pin<&mut S>, closure: F) -> Poll<R>
+  where S: Unpin, F: FnOnce(S) -> (S, Poll<R>) {
+    ::take_and_return(Pin::get_mut(state), closure)
+}
+
+/// Transitions a pinned, `Unpin` state, returning the `Poll<Option<T>>` produced by the closure, as
+/// `Stream::poll_next()` does.
+#[track_caller]
+pub fn poll_next<S, T, F>(state: Pin<&mut S>, closure: F) -> Poll<Option<T>>
+  where S: Unpin, F: FnOnce(S) -> (S, Poll<Option<T>>) {
+    ::take_and_return(Pin::get_mut(state), closure)
+}
+
+/// Transitions a state, returning the `Option<T>` produced by the closure, as `Iterator::next()` does.
+///
+/// # Example
+/// ```
+/// enum Pairs<I: Iterator> {
+///     Empty(I),
+///     Half(I, I::Item),
+///     Done,
+/// }
+///
+/// impl<I: Iterator> Iterator for Pairs<I> {
+///     type Item = (I::Item, I::Item);
+///
+///     fn next(&mut self) -> Option<Self::Item> {
+///         take_mut::state::next(self, |state| match state {
+///             Pairs::Empty(mut iter) => match (iter.next(), iter.next()) {
+///                 (Some(a), Some(b)) => (Pairs::Empty(iter), Some((a, b))),
+///                 _ => (Pairs::Done, None),
+///             },
+///             Pairs::Half(mut iter, a) => match iter.next() {
+///                 Some(b) => (Pairs::Empty(iter), Some((a, b))),
+///                 None => (Pairs::Done, None),
+///             },
+///             Pairs::Done => (Pairs::Done, None),
+///         })
+///     }
+/// }
+///
+/// let pairs: Vec<_> = Pairs::Empty(1..6).collect();
+/// assert_eq!(pairs, [(1, 2), (3, 4)]);
+/// ```
+#[track_caller]
+pub fn next<S, T, F>(state: &mut S, closure: F) -> Option<T>
+  where F: FnOnce(S) -> (S, Option<T>) {
+    ::take_and_return(state, closure)
+}
+
+
+#[test]
+fn state_poll_next() {
+    struct Count(u32);
+    fn poll(count: &mut Count) -> Poll<Option<u32>> {
+        poll_next(Pin::new(count), |Count(n)| {
+            if n < 2 {
+                (Count(n + 1), Poll::Ready(Some(n)))
+            } else {
+                (Count(n), Poll::Ready(None))
+            }
+        })
+    }
+    let mut count = Count(0);
+    assert_eq!(poll(&mut count), Poll::Ready(Some(0)));
+    assert_eq!(poll(&mut count), Poll::Ready(Some(1)));
+    assert_eq!(poll(&mut count), Poll::Ready(None));
+}
@@ -0,0 +1,116 @@
+//! Transform the contents of a `Mutex<T>` or `RwLock<T>` by value, relying on lock poisoning instead of
+//! terminating the process.
+//!
+//! If the closure panics, a recovery value is written into the lock and the panic continues. Unwinding drops the
+//! lock guard, which poisons the lock as usual, so later users can tell something went wrong while the lock still
+//! holds a valid `T`.
+//!
+//! Like `Mutex::lock()` and `RwLock::write()`, every function returns a `PoisonError` if the lock was already
+//! poisoned, without calling any closure.
+
+use std::sync::{Mutex, MutexGuard, PoisonError, RwLock, RwLockWriteGuard};
+
+use placeholder::Placeholder;
+
+/// Locks `mutex` and transforms its contents with `closure`, writing the result of `recover` if it panics.
+///
+/// # Important
+/// Will terminate the program (see `policy::DefaultPolicy`) if `recover` itself panics.
+///
+/// # Example
+/// ```
+/// use std::panic;
+/// use std::sync::Mutex;
+///
+/// let mutex = Mutex::new(vec![1, 2, 3]);
+/// let res = panic::catch_unwind(|| {
+///     take_mut::sync::take_locked(&mutex, Vec::new, |v| {
+///         drop(v);
+///         panic!("oops");
+///     })
+/// });
+/// assert!(res.is_err());
+/// assert!(mutex.is_poisoned());
+/// assert!(mutex.lock().unwrap_err().into_inner().is_empty());
+/// ```
+#[track_caller]
+pub fn take_locked<'a, T, F, R>(mutex: &'a Mutex<T>, recover: R, closure: F) -> Result<(), PoisonError<MutexGuard<'a, T>>>
+  where F: FnOnce(T) -> T, R: FnOnce() -> T {
+    let mut guard = mutex.lock()?;
+    ::take_or_recover(&mut *guard, recover, closure);
+    Ok(())
+}
+
+/// Like `take_locked()`, using `T::default()` to recover.
+#[track_caller]
+pub fn take_locked_or_default<'a, T, F>(mutex: &'a Mutex<T>, closure: F) -> Result<(), PoisonError<MutexGuard<'a, T>>>
+  where T: Default, F: FnOnce(T) -> T {
+    take_locked(mutex, T::default, closure)
+}
+
+/// Like `take_locked()`, using `T::placeholder()` to recover.
+#[track_caller]
+pub fn take_locked_or_placeholder<'a, T, F>(mutex: &'a Mutex<T>, closure: F) -> Result<(), PoisonError<MutexGuard<'a, T>>>
+  where T: Placeholder, F: FnOnce(T) -> T {
+    take_locked(mutex, T::placeholder, closure)
+}
+
+/// Locks `lock` for writing and transforms its contents with `closure`, writing the result of `recover` if it
+/// panics.
+///
+/// # Important
+/// Will terminate the program (see `policy::DefaultPolicy`) if `recover` itself panics.
+#[track_caller]
+pub fn take_write<'a, T, F, R>(lock: &'a RwLock<T>, recover: R, closure: F) -> Result<(), PoisonError<RwLockWriteGuard<'a, T>>>
+  where F: FnOnce(T) -> T, R: FnOnce() -> T {
+    let mut guard = lock.write()?;
+    ::take_or_recover(&mut *guard, recover, closure);
+    Ok(())
+}
+
+/// Like `take_write()`, using `T::default()` to recover.
+#[track_caller]
+pub fn take_write_or_default<'a, T, F>(lock: &'a RwLock<T>, closure: F) -> Result<(), PoisonError<RwLockWriteGuard<'a, T>>>
+  where T: Default, F: FnOnce(T) -> T {
+    take_write(lock, T::default, closure)
+}
+
+/// Like `take_write()`, using `T::placeholder()` to recover.
+#[track_caller]
+pub fn take_write_or_placeholder<'a, T, F>(lock: &'a RwLock<T>, closure: F) -> Result<(), PoisonError<RwLockWriteGuard<'a, T>>>
+  where T: Placeholder, F: FnOnce(T) -> T {
+    take_write(lock, T::placeholder, closure)
+}
+
+
+#[test]
+fn take_locked_poisons() {
+    use std::panic;
+    use std::string::String;
+    let mutex = Mutex::new(String::from("foo"));
+    take_locked_or_default(&mutex, |s| s + "bar").unwrap();
+    assert_eq!(*mutex.lock().unwrap(), "foobar");
+
+    let res = panic::catch_unwind(|| {
+        take_locked(&mutex, || String::from("recovered"), |_| panic!("expected panic"))
+    });
+    assert!(res.is_err());
+    assert!(mutex.is_poisoned());
+    let err = take_locked_or_default(&mutex, |s| s).unwrap_err();
+    assert_eq!(*err.into_inner(), "recovered");
+}
+
+#[test]
+fn take_write_poisons() {
+    use std::panic;
+    let lock = RwLock::new(1);
+    take_write_or_placeholder(&lock, |x| x + 1).unwrap();
+    assert_eq!(*lock.read().unwrap(), 2);
+
+    let res = panic::catch_unwind(|| {
+        take_write_or_placeholder(&lock, |_| -> i32 { panic!("expected panic") })
+    });
+    assert!(res.is_err());
+    assert!(lock.is_poisoned());
+    assert_eq!(*lock.read().unwrap_err().into_inner(), 0);
+}
@@ -0,0 +1,293 @@
+//! Procedural macros for `take_mut`. Use them through the `take_mut` crate with the `macros` feature enabled.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{TokenStream as TokenStream2, TokenTree};
+use quote::{format_ident, quote, ToTokens};
+use syn::{parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Expr, Fields, FnArg, Ident, ImplItemFn, ItemFn, Meta, Path, ReturnType, Type};
+
+/// Implements `take_mut::Placeholder` for a struct or an enum.
+///
+/// Every field is set to `Placeholder::placeholder()`, unless it has a `#[placeholder = literal]` or
+/// `#[placeholder(expr)]` attribute, in which case it is set to that value. For an enum, exactly one variant must be
+/// marked `#[placeholder]`, and is built the same way.
+///
+/// Fields using `Placeholder::placeholder()` whose type mentions a type parameter get a `FieldType: Placeholder` bound.
+///
+/// ```
+/// use std::net::TcpStream;
+/// use take_mut::Placeholder;
+///
+/// #[derive(Placeholder)]
+/// enum Connection {
+///     Open { socket: TcpStream },
+///     #[placeholder]
+///     Closed { #[placeholder(String::from("placeholder"))] reason: String, #[placeholder = 3] retries: u32 },
+/// }
+///
+/// match Connection::placeholder() {
+///     Connection::Closed { reason, retries } => assert_eq!((reason.as_str(), retries), ("placeholder", 3)),
+///     Connection::Open { .. } => unreachable!(),
+/// }
+/// ```
+#[proc_macro_derive(Placeholder, attributes(placeholder))]
+pub fn derive_placeholder(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let params: Vec<_> = input.generics.type_params().map(|param| param.ident.clone()).collect();
+    let mut bounds = Vec::new();
+    let body = match input.data {
+        Data::Struct(ref data) => construct(quote!(#name), &data.fields, &params, &mut bounds),
+        Data::Enum(ref data) => {
+            let marked: Vec<_> = data.variants.iter()
+                .filter(|variant| variant.attrs.iter().any(|attr| attr.path().is_ident("placeholder")))
+                .collect();
+            match marked[..] {
+                [variant] => match placeholder_attr(&variant.attrs) {
+                    Ok(None) => {
+                        let variant_name = &variant.ident;
+                        construct(quote!(#name::#variant_name), &variant.fields, &params, &mut bounds)
+                    }
+                    Ok(Some(expr)) => Err(syn::Error::new_spanned(expr, "expected `#[placeholder]` on a variant")),
+                    Err(err) => Err(err),
+                },
+                [] => Err(syn::Error::new_spanned(name, "#[derive(Placeholder)] needs one variant marked `#[placeholder]`")),
+                [_, extra, ..] => Err(syn::Error::new_spanned(&extra.ident, "only one variant can be marked `#[placeholder]`")),
+            }
+        }
+        Data::Union(_) => Err(syn::Error::new_spanned(name, "#[derive(Placeholder)] does not support unions")),
+    };
+    let body = match body {
+        Ok(body) => body,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let mut generics = input.generics.clone();
+    let where_clause = generics.make_where_clause();
+    for ty in bounds {
+        where_clause.predicates.push(parse_quote!(#ty: ::take_mut::Placeholder));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let expanded = quote! {
+        impl #impl_generics ::take_mut::Placeholder for #name #ty_generics #where_clause {
+            fn placeholder() -> Self {
+                #body
+            }
+        }
+    };
+    expanded.into()
+}
+
+/// Builds `path` from `fields`, using their `#[placeholder]` values or else `Placeholder::placeholder()`.
+///
+/// The types of fields using `Placeholder::placeholder()` that mention one of `params` are added to `bounds`.
+fn construct(path: TokenStream2, fields: &Fields, params: &[Ident], bounds: &mut Vec<Type>) -> syn::Result<TokenStream2> {
+    let values = fields.iter()
+        .map(|field| match placeholder_attr(&field.attrs)? {
+            Some(expr) => Ok(quote!(#expr)),
+            None => {
+                if mentions_any(field.ty.to_token_stream(), params) {
+                    bounds.push(field.ty.clone());
+                }
+                Ok(quote!(::take_mut::Placeholder::placeholder()))
+            }
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+    Ok(match *fields {
+        Fields::Named(ref fields) => {
+            let names = fields.named.iter().map(|field| &field.ident);
+            quote!(#path { #(#names: #values),* })
+        }
+        Fields::Unnamed(_) => quote!(#path(#(#values),*)),
+        Fields::Unit => path,
+    })
+}
+
+/// Whether `tokens` contain any of `idents`, looking into groups.
+fn mentions_any(tokens: TokenStream2, idents: &[Ident]) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Ident(ref ident) => idents.contains(ident),
+        TokenTree::Group(ref group) => mentions_any(group.stream(), idents),
+        _ => false,
+    })
+}
+
+/// Returns the value of `#[placeholder = literal]` or `#[placeholder(expr)]`, and `None` for a bare `#[placeholder]` or no
+/// attribute.
+fn placeholder_attr(attrs: &[Attribute]) -> syn::Result<Option<Expr>> {
+    let mut value = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("placeholder")) {
+        if value.is_some() {
+            return Err(syn::Error::new_spanned(attr, "duplicate `#[placeholder]` attribute"));
+        }
+        value = match attr.meta {
+            Meta::Path(_) => Some(None),
+            Meta::NameValue(ref meta) => Some(Some(meta.value.clone())),
+            Meta::List(ref list) => Some(Some(list.parse_args()?)),
+        };
+    }
+    Ok(value.flatten())
+}
+
+/// Registers a function to abort the process when `take_mut` needs to terminate without `std`.
+///
+/// The function must take no arguments and return `!`. It is only called if the `abort-handler` feature of
+/// `take_mut` is enabled, in which case exactly one function must be registered in the final binary.
+///
+/// ```ignore
+/// #[take_mut::abort_handler]
+/// fn abort() -> ! {
+///     cortex_m::peripheral::SCB::sys_reset()
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn abort_handler(args: TokenStream, input: TokenStream) -> TokenStream {
+    if !args.is_empty() {
+        let args = proc_macro2::TokenStream::from(args);
+        return syn::Error::new_spanned(args, "#[abort_handler] takes no arguments")
+            .to_compile_error()
+            .into();
+    }
+    let item = parse_macro_input!(input as ItemFn);
+    let never = match item.sig.output {
+        ReturnType::Type(_, ref ty) => matches!(**ty, Type::Never(_)),
+        ReturnType::Default => false,
+    };
+    if !item.sig.inputs.is_empty() || !never {
+        return syn::Error::new_spanned(&item.sig, "#[abort_handler] function must have signature `fn() -> !`")
+            .to_compile_error()
+            .into();
+    }
+    let name = &item.sig.ident;
+    let expanded = quote! {
+        #item
+
+        #[doc(hidden)]
+        #[unsafe(no_mangle)]
+        pub fn __take_mut_abort_handler() -> ! {
+            #name()
+        }
+    };
+    expanded.into()
+}
+
+/// Generates a `&mut self` counterpart for a method taking `self` by value.
+///
+/// For `fn name(self, args...) -> Self`, this adds `fn name_mut(&mut self, args...)`, calling the method
+/// through `take_mut::take()`. For `fn name(self, args...) -> (Self, R)`, the counterpart returns `R`
+/// and goes through `take_mut::take_and_return()`.
+///
+/// With `#[by_value(recover = path)]`, the `*_or_recover()` functions are used instead, with `path`
+/// producing the replacement value. This requires the `std` feature of `take_mut`.
+///
+/// ```
+/// struct Counter(Vec<u32>);
+///
+/// impl Counter {
+///     fn empty() -> Self {
+///         Counter(Vec::new())
+///     }
+///
+///     #[take_mut::by_value]
+///     fn push(mut self, n: u32) -> Self {
+///         self.0.push(n);
+///         self
+///     }
+///
+///     #[take_mut::by_value(recover = Self::empty)]
+///     fn pop(mut self) -> (Self, Option<u32>) {
+///         let n = self.0.pop();
+///         (self, n)
+///     }
+/// }
+///
+/// let mut counter = Counter(vec![]);
+/// counter.push_mut(1);
+/// assert_eq!(counter.pop_mut(), Some(1));
+/// ```
+#[proc_macro_attribute]
+pub fn by_value(args: TokenStream, input: TokenStream) -> TokenStream {
+    let mut recover: Option<Path> = None;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("recover") {
+            recover = Some(meta.value()?.parse()?);
+            Ok(())
+        } else {
+            Err(meta.error("expected `recover = path`"))
+        }
+    });
+    parse_macro_input!(args with parser);
+    let item = parse_macro_input!(input as ImplItemFn);
+    match by_value_wrapper(&item, recover.as_ref()) {
+        Ok(wrapper) => quote!(#item #wrapper).into(),
+        Err(err) => {
+            let err = err.to_compile_error();
+            quote!(#item #err).into()
+        }
+    }
+}
+
+fn by_value_wrapper(item: &ImplItemFn, recover: Option<&Path>) -> syn::Result<TokenStream2> {
+    let sig = &item.sig;
+    if let Some(asyncness) = sig.asyncness {
+        return Err(syn::Error::new_spanned(asyncness, "#[by_value] does not support async methods"));
+    }
+    match sig.inputs.first() {
+        Some(FnArg::Receiver(receiver)) if receiver.reference.is_none() && is_self(&receiver.ty) => {}
+        _ => return Err(syn::Error::new_spanned(sig, "#[by_value] method must take `self` by value")),
+    }
+    let returns_value = match sig.output {
+        ReturnType::Type(_, ref ty) => match **ty {
+            ref ty if is_self(ty) => false,
+            Type::Tuple(ref tuple) if tuple.elems.len() == 2 && is_self(&tuple.elems[0]) => true,
+            _ => return Err(syn::Error::new_spanned(ty, "#[by_value] method must return `Self` or `(Self, R)`")),
+        },
+        ReturnType::Default => {
+            return Err(syn::Error::new_spanned(sig, "#[by_value] method must return `Self` or `(Self, R)`"));
+        }
+    };
+
+    let name = &sig.ident;
+    let name_mut = format_ident!("{}_mut", name);
+    let vis = &item.vis;
+    let unsafety = &sig.unsafety;
+    let (impl_generics, _, where_clause) = sig.generics.split_for_impl();
+    let args: Vec<_> = (1..sig.inputs.len()).map(|i| format_ident!("__arg{}", i)).collect();
+    let arg_types = sig.inputs.iter().skip(1).map(|arg| match *arg {
+        FnArg::Typed(ref arg) => &arg.ty,
+        FnArg::Receiver(_) => unreachable!("only the first argument can be a receiver"),
+    });
+    let output = match sig.output {
+        ReturnType::Type(_, ref ty) => match **ty {
+            Type::Tuple(ref tuple) if returns_value => {
+                let ret = &tuple.elems[1];
+                quote!(-> #ret)
+            }
+            _ => quote!(),
+        },
+        ReturnType::Default => quote!(),
+    };
+    let call = quote!(|this| #unsafety { Self::#name(this, #(#args),*) });
+    let body = match (returns_value, recover) {
+        (false, None) => quote!(::take_mut::take(self, #call)),
+        (true, None) => quote!(::take_mut::take_and_return(self, #call)),
+        (false, Some(recover)) => quote!(::take_mut::take_or_recover(self, #recover, #call)),
+        (true, Some(recover)) => quote!(::take_mut::take_and_return_or_recover(self, #recover, #call)),
+    };
+    let doc = format!("Like [`Self::{}`], but through `&mut self`.", name);
+    Ok(quote! {
+        #[doc = #doc]
+        #[track_caller]
+        #vis #unsafety fn #name_mut #impl_generics (&mut self, #(#args: #arg_types),*) #output #where_clause {
+            #body
+        }
+    })
+}
+
+/// Whether `ty` is literally `Self`.
+fn is_self(ty: &Type) -> bool {
+    match *ty {
+        Type::Path(ref path) => path.qself.is_none() && path.path.is_ident("Self"),
+        _ => false,
+    }
+}
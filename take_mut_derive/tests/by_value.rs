@@ -0,0 +1,49 @@
+use std::panic;
+
+#[derive(Debug, PartialEq)]
+struct Counter(Vec<u32>);
+
+impl Counter {
+    fn empty() -> Self {
+        Counter(Vec::new())
+    }
+
+    #[take_mut::by_value]
+    fn push(mut self, n: u32, times: usize) -> Self {
+        self.0.extend(std::iter::repeat_n(n, times));
+        self
+    }
+
+    #[take_mut::by_value]
+    fn pop(mut self) -> (Self, Option<u32>) {
+        let n = self.0.pop();
+        (self, n)
+    }
+
+    #[take_mut::by_value(recover = Self::empty)]
+    fn checked_push(mut self, n: u32) -> (Self, usize) {
+        assert!(n != 0, "expected panic");
+        self.0.push(n);
+        let len = self.0.len();
+        (self, len)
+    }
+}
+
+#[test]
+fn generates_mut_methods() {
+    let mut counter = Counter::empty();
+    counter.push_mut(1, 2);
+    assert_eq!(counter.pop_mut(), Some(1));
+    assert_eq!(counter.checked_push_mut(2), 2);
+    assert_eq!(counter, Counter(vec![1, 2]));
+}
+
+#[test]
+fn recovers_in_mut_method() {
+    let mut counter = Counter(vec![1]);
+    let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        counter.checked_push_mut(0);
+    }));
+    assert!(res.is_err());
+    assert_eq!(counter, Counter::empty());
+}
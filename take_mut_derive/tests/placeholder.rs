@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::panic;
+
+use take_mut::Placeholder;
+
+#[derive(Debug, PartialEq, Placeholder)]
+struct Named<T> {
+    items: Vec<T>,
+    name: String,
+    index: HashMap<String, usize>,
+    count: usize,
+}
+
+#[derive(Debug, PartialEq, Placeholder)]
+struct Tuple(Option<u8>, bool);
+
+#[derive(Debug, PartialEq, Placeholder)]
+struct Unit;
+
+#[derive(Debug, PartialEq, Placeholder)]
+struct WithDefaults {
+    #[placeholder(String::from("unnamed"))]
+    name: String,
+    #[placeholder = 3]
+    retries: u32,
+    open: bool,
+}
+
+#[derive(Debug, PartialEq, Placeholder)]
+enum Connection {
+    Open(Vec<u8>),
+    #[placeholder]
+    Closed { reason: Option<String>, #[placeholder = 1] attempts: u32 },
+}
+
+#[test]
+fn derives_placeholder() {
+    let named: Named<u32> = Placeholder::placeholder();
+    assert_eq!(named, Named { items: vec![], name: String::new(), index: HashMap::new(), count: 0 });
+    assert_eq!(Tuple::placeholder(), Tuple(None, false));
+    assert_eq!(Unit::placeholder(), Unit);
+    assert_eq!(WithDefaults::placeholder(), WithDefaults { name: String::from("unnamed"), retries: 3, open: false });
+    assert_eq!(Connection::placeholder(), Connection::Closed { reason: None, attempts: 1 });
+}
+
+#[test]
+fn recovers_with_derived_placeholder() {
+    let mut tuple = Tuple(Some(1), true);
+    let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        take_mut::take_or_placeholder(&mut tuple, |_| panic!("expected panic"));
+    }));
+    assert!(res.is_err());
+    assert_eq!(tuple, Tuple(None, false));
+}
+
+#[test]
+fn recovers_enum_with_placeholder_variant() {
+    let mut conn = Connection::Open(vec![1]);
+    let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        take_mut::take_and_return_or_placeholder(&mut conn, |_| -> (Connection, ()) { panic!("expected panic") });
+    }));
+    assert!(res.is_err());
+    assert_eq!(conn, Connection::Closed { reason: None, attempts: 1 });
+}
+
+#[derive(Placeholder)]
+struct Generic<T, U> {
+    items: Vec<T>,
+    #[placeholder(Vec::new())]
+    others: Vec<U>,
+}
+
+#[test]
+fn derives_bounds_per_field() {
+    use std::cell::Cell;
+    // `Cell<u8>` isn't `Placeholder`, but neither field needs it to be.
+    let generic: Generic<Cell<u8>, Cell<u8>> = Placeholder::placeholder();
+    assert!(generic.items.is_empty() && generic.others.is_empty());
+}